@@ -1,11 +1,11 @@
 use clap::Parser;
-use orfile::Orfile;
+use orfile::{Orfile, OrfileConfig};
 use serde::{Deserialize, Serialize};
 
 /// The arguments for the add command
 ///
 /// We define this as a separate struct because Orfile requires separate config structs to allow composability and discretion between mandatory and `using` enabled fields.
-#[derive(Parser, Debug, Clone, Serialize, Deserialize)]
+#[derive(Parser, Debug, Clone, Serialize, Deserialize, OrfileConfig)]
 #[clap(rename_all = "kebab-case")]
 pub struct AddArgs {
 	/// The left number
@@ -22,6 +22,7 @@ pub struct AddArgs {
 
 #[derive(Parser, Debug, Clone, Orfile)]
 #[clap(rename_all = "kebab-case")]
+#[orfile(discover = "tool/add")]
 pub struct Add {
 	#[orfile(config)]
 	#[clap(flatten)]
@@ -36,3 +37,389 @@ impl Add {
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// Each command lives in its own module, since the derive generates modules such as `using`
+	mod named {
+		use super::*;
+
+		/// Flags shared by several commands, which are neither config nor serializable.
+		#[derive(clap::Args, Debug, Clone)]
+		pub struct Global {
+			#[clap(long)]
+			pub verbose: bool,
+		}
+
+		/// An add command with fields which are not part of the config.
+		#[derive(Parser, Debug, Clone, Orfile)]
+		#[clap(rename_all = "kebab-case")]
+		pub struct NamedAdd {
+			#[orfile(config)]
+			#[clap(flatten)]
+			pub args: AddArgs,
+			#[clap(long)]
+			pub name: String,
+			#[clap(flatten)]
+			pub global: Global,
+		}
+
+		#[tokio::test]
+		async fn test_where_command_keeps_other_fields() -> Result<(), anyhow::Error> {
+			let args = [
+				"tool",
+				"named-add",
+				"explain",
+				"--output-format",
+				"where",
+				"--name",
+				"n",
+				"--verbose",
+				"--",
+				"--left",
+				"1",
+				"--right",
+				"2",
+			]
+			.map(String::from);
+			let explain = explain::NamedAdd::try_parse_from(&args[2..])?;
+
+			let command = explain.using.where_command_from(args.to_vec()).await?;
+			assert_eq!(
+				command,
+				[
+					"tool",
+					"named-add",
+					"where",
+					"--left",
+					"1",
+					"--right",
+					"2",
+					"--name",
+					"n",
+					"--verbose"
+				]
+			);
+			Ok(())
+		}
+
+		#[tokio::test]
+		async fn test_explain_shows_coerced_values() -> Result<(), anyhow::Error> {
+			let using = using::NamedAdd::try_parse_from([
+				"using", "--name", "n", "--", "--left", "1", "--right", "2",
+			])?;
+
+			let explanation = using.explain().await?;
+			assert_eq!(explanation.to_json()[0]["value"], orfile::serde_json::json!(1));
+			Ok(())
+		}
+
+		#[tokio::test]
+		async fn test_explain_rejects_save_resolved() -> Result<(), anyhow::Error> {
+			let path = std::env::temp_dir().join("orfile-explain-save-resolved.json");
+			let _ = std::fs::remove_file(&path);
+			let explain = explain::NamedAdd::try_parse_from([
+				"explain",
+				"--save-resolved",
+				path.to_str().unwrap(),
+				"--name",
+				"n",
+				"--",
+				"--left",
+				"1",
+				"--right",
+				"2",
+			])?;
+
+			assert!(explain.run().await.is_err());
+			assert!(!path.exists());
+			Ok(())
+		}
+	}
+
+	mod profiled {
+		use super::*;
+
+		/// A config type with a field named `profile`.
+		#[derive(Parser, Debug, Clone, Serialize, Deserialize, OrfileConfig)]
+		pub struct ProfileArgs {
+			#[clap(long)]
+			pub profile: String,
+		}
+
+		#[derive(Parser, Debug, Clone, Orfile)]
+		#[orfile(env_prefix = "PROFILED")]
+		pub struct Profiled {
+			#[orfile(config)]
+			#[clap(flatten)]
+			pub args: ProfileArgs,
+		}
+
+		#[tokio::test]
+		async fn test_profile_env_var_sets_profile_field() -> Result<(), anyhow::Error> {
+			std::env::set_var("PROFILED_PROFILE", "dev");
+
+			let profiled = using::Profiled::try_parse_from(["using"])?.resolve().await?;
+			assert_eq!(profiled.args.profile, "dev");
+			Ok(())
+		}
+	}
+
+	mod clashing {
+		use super::*;
+
+		/// An add command with its own field named like a flag `using` adds.
+		#[derive(Parser, Debug, Clone, Orfile)]
+		pub struct ClashingAdd {
+			#[orfile(config)]
+			#[clap(flatten)]
+			pub args: AddArgs,
+			#[clap(long)]
+			pub profile: String,
+		}
+
+		#[tokio::test]
+		async fn test_generated_flags_do_not_clash() -> Result<(), anyhow::Error> {
+			let using = using::ClashingAdd::try_parse_from([
+				"using",
+				"--profile",
+				"mine",
+				"--orfile-profile",
+				"prod",
+				"--",
+				"--left",
+				"1",
+				"--right",
+				"2",
+			])?;
+			assert_eq!(using.orfile_profile.as_deref(), Some("prod"));
+
+			let clashing = using::ClashingAdd::try_parse_from([
+				"using",
+				"--profile",
+				"mine",
+				"--",
+				"--left",
+				"1",
+				"--right",
+				"2",
+			])?
+			.resolve()
+			.await?;
+			assert_eq!(clashing.profile, "mine");
+			Ok(())
+		}
+	}
+
+	mod strict {
+		use super::*;
+
+		#[derive(Parser, Debug, Clone, Serialize, Deserialize, OrfileConfig)]
+		pub struct Left {
+			#[clap(long)]
+			pub left: u64,
+		}
+
+		#[derive(Parser, Debug, Clone, Serialize, Deserialize, OrfileConfig)]
+		pub struct Right {
+			#[clap(long)]
+			pub right: u64,
+		}
+
+		/// A strict command whose args are split across two config fields.
+		#[derive(Parser, Debug, Clone, Orfile)]
+		#[orfile(strict, no_env)]
+		pub struct StrictAdd {
+			#[orfile(config)]
+			#[clap(flatten)]
+			pub a: Left,
+			#[orfile(config)]
+			#[clap(flatten)]
+			pub b: Right,
+		}
+
+		#[tokio::test]
+		async fn test_strict_accepts_keys_of_any_config_field() -> Result<(), anyhow::Error> {
+			let add =
+				using::StrictAdd::try_parse_from(["using", "--", "--left", "1", "--right", "2"])?
+					.resolve()
+					.await?;
+			assert_eq!((add.a.left, add.b.right), (1, 2));
+
+			let err = using::StrictAdd::try_parse_from([
+				"using", "--", "--left", "1", "--right", "2", "--rigth", "3",
+			])?
+			.resolve()
+			.await
+			.unwrap_err();
+			assert!(format!("{:#}", err).contains("unknown key `rigth`"), "{:#}", err);
+			Ok(())
+		}
+	}
+
+	mod flattened {
+		use super::*;
+
+		#[derive(Parser, Debug, Clone, Serialize, Deserialize, OrfileConfig)]
+		pub struct Label {
+			#[clap(long)]
+			pub name: String,
+		}
+
+		#[derive(Parser, Debug, Clone, Serialize, Deserialize, OrfileConfig)]
+		pub struct LabeledArgs {
+			#[clap(long)]
+			pub left: u64,
+			#[serde(flatten)]
+			#[clap(flatten)]
+			pub label: Label,
+		}
+
+		/// A strict command whose config type flattens another.
+		#[derive(Parser, Debug, Clone, Orfile)]
+		#[orfile(strict, no_env)]
+		pub struct LabeledAdd {
+			#[orfile(config)]
+			#[clap(flatten)]
+			pub args: LabeledArgs,
+		}
+
+		#[tokio::test]
+		async fn test_flattened_fields_are_spliced_in() -> Result<(), anyhow::Error> {
+			let names: Vec<_> = LabeledArgs::fields().iter().map(|field| field.name).collect();
+			assert_eq!(names, vec!["left", "name"]);
+
+			let schema = orfile::schema::for_config::<LabeledArgs>(true);
+			assert_eq!(schema["required"], orfile::serde_json::json!(["left", "name"]));
+
+			let add =
+				using::LabeledAdd::try_parse_from(["using", "--", "--left", "1", "--name", "n"])?
+					.resolve()
+					.await?;
+			assert_eq!(add.args.label.name, "n");
+			Ok(())
+		}
+	}
+
+	mod underived {
+		use super::*;
+
+		/// A config type from a crate which does not know about OrfileConfig.
+		#[derive(Parser, Debug, Clone, Serialize, Deserialize)]
+		pub struct PlainArgs {
+			#[clap(long)]
+			pub left: u64,
+		}
+
+		#[derive(Parser, Debug, Clone, Orfile)]
+		#[orfile(strict, no_env)]
+		pub struct PlainAdd {
+			#[orfile(config)]
+			#[clap(flatten)]
+			pub args: PlainArgs,
+		}
+
+		#[tokio::test]
+		async fn test_config_type_without_orfile_config() -> Result<(), anyhow::Error> {
+			let add = using::PlainAdd::try_parse_from(["using", "--", "--left", "1"])?
+				.resolve()
+				.await?;
+			assert_eq!(add.args.left, 1);
+
+			let err = schema::PlainAdd::try_parse_from(["schema"])?.schema().unwrap_err();
+			assert_eq!(err.to_string(), "PlainArgs does not derive OrfileConfig");
+			Ok(())
+		}
+	}
+
+	mod files {
+		use super::*;
+
+		/// A command which discovers its config file under `$XDG_CONFIG_HOME`.
+		#[derive(Parser, Debug, Clone, Orfile)]
+		#[orfile(no_env, discover = "orfile-tool-tests/add")]
+		pub struct FileAdd {
+			#[orfile(config)]
+			#[clap(flatten)]
+			pub args: AddArgs,
+		}
+
+		/// A directory of its own under the temp dir for each test.
+		fn temp_dir(name: &str) -> std::path::PathBuf {
+			let dir = std::env::temp_dir().join("orfile-tool-tests").join(name);
+			let _ = std::fs::remove_dir_all(&dir);
+			std::fs::create_dir_all(&dir).unwrap();
+			dir
+		}
+
+		fn write(path: &std::path::Path, contents: &str) -> String {
+			std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+			std::fs::write(path, contents).unwrap();
+			path.display().to_string()
+		}
+
+		#[tokio::test]
+		async fn test_discovered_file_is_merged() -> Result<(), anyhow::Error> {
+			let dir = temp_dir("discover");
+			write(&dir.join("orfile-tool-tests/add.json"), r#"{ "left": 1, "right": 2 }"#);
+			std::env::set_var("XDG_CONFIG_HOME", &dir);
+
+			let add = using::FileAdd::try_parse_from(["using", "--", "--right", "3"])?
+				.resolve()
+				.await?;
+			assert_eq!((add.args.left, add.args.right), (1, 3));
+			Ok(())
+		}
+
+		#[tokio::test]
+		async fn test_validate_schema_rejects_mistyped_file() -> Result<(), anyhow::Error> {
+			let dir = temp_dir("validate-schema");
+			let path = write(&dir.join("add.json"), r#"{ "left": "one", "right": 2 }"#);
+
+			let err = using::FileAdd::try_parse_from([
+				"using",
+				"--args-path",
+				&path,
+				"--validate-schema",
+			])?
+			.resolve()
+			.await
+			.unwrap_err();
+			assert!(
+				format!("{:#}", err).contains("does not match its schema at `left`"),
+				"{:#}",
+				err
+			);
+			Ok(())
+		}
+
+		#[tokio::test]
+		async fn test_save_resolved_replays() -> Result<(), anyhow::Error> {
+			let dir = temp_dir("save-resolved");
+			let path = write(&dir.join("add.json"), r#"{ "left": 1, "right": 2 }"#);
+			let saved = dir.join("resolved.toml").display().to_string();
+
+			using::FileAdd::try_parse_from([
+				"using",
+				"--args-path",
+				&path,
+				"--save-resolved",
+				&saved,
+				"--",
+				"--right",
+				"4",
+			])?
+			.resolve()
+			.await?;
+
+			// The saved file alone resolves to the same config
+			let add = using::FileAdd::try_parse_from(["using", "--args-path", &saved])?
+				.resolve()
+				.await?;
+			assert_eq!((add.args.left, add.args.right), (1, 4));
+			Ok(())
+		}
+	}
+}
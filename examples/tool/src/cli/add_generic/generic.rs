@@ -1,5 +1,5 @@
 use clap::{ArgMatches, Command, CommandFactory, FromArgMatches, Parser};
-use orfile::Orfile;
+use orfile::{Orfile, OrfileConfig};
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 use std::str::FromStr;
@@ -20,7 +20,7 @@ where
 }
 
 /// The arguments for the add command
-#[derive(Debug, Clone, Parser, Serialize, Deserialize)]
+#[derive(Debug, Clone, Parser, Serialize, Deserialize, OrfileConfig)]
 pub struct AddArgs {
 	/// The left number
 	#[clap(long)]
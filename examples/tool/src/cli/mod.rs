@@ -13,7 +13,10 @@ impl Tool {
 	pub async fn execute(&self) -> Result<(), anyhow::Error> {
 		match self {
 			Tool::Add(add) => {
-				add.clone().resolve().await?.execute().await?;
+				// `explain`, `schema` and `init` have already printed their output
+				if let Some(add) = add.clone().resolve().await? {
+					add.execute().await?;
+				}
 			}
 		}
 
@@ -0,0 +1,24 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[test]
+fn test_config_file_from_stdin() {
+	let dir = std::env::temp_dir().join("orfile-tool-tests").join("stdin");
+	std::fs::create_dir_all(&dir).unwrap();
+
+	// An empty home keeps discovered config files out of the run
+	let mut child = Command::new(env!("CARGO_BIN_EXE_tool"))
+		.args(["add", "using", "--args-path", "-", "--", "--right", "2"])
+		.current_dir(&dir)
+		.env("HOME", &dir)
+		.env("XDG_CONFIG_HOME", &dir)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.spawn()
+		.unwrap();
+	child.stdin.take().unwrap().write_all(br#"{ "left": 1 }"#).unwrap();
+
+	let output = child.wait_with_output().unwrap();
+	assert!(output.status.success());
+	assert_eq!(String::from_utf8(output.stdout).unwrap().lines().last(), Some("3"));
+}
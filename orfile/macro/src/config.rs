@@ -0,0 +1,364 @@
+use heck::{
+	ToKebabCase, ToLowerCamelCase, ToShoutyKebabCase, ToShoutySnakeCase, ToSnakeCase,
+	ToUpperCamelCase,
+};
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use proc_macro_error::abort;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::token::Comma;
+use syn::{
+	parse_macro_input, Attribute, Data, DeriveInput, Expr, ExprLit, Fields, GenericArgument, Lit,
+	LitStr, Meta, PathArguments, Type,
+};
+
+/// The `#[orfile(...)]` options of a config field.
+#[derive(Default)]
+struct FieldOptions {
+	/// Set by `#[orfile(merge = "append")]`.
+	append: bool,
+	/// Set by `#[orfile(nested)]`.
+	nested: bool,
+	/// Set by `#[orfile(env = "...")]`.
+	env: Option<String>,
+	/// Set by each `#[orfile(alias = "...")]`.
+	aliases: Vec<String>,
+	/// Set by `#[orfile(skip_env)]`.
+	skip_env: bool,
+	/// Set by `#[orfile(path)]`.
+	path: bool,
+}
+
+impl FieldOptions {
+	fn parse(attrs: &[Attribute]) -> Self {
+		let mut options = Self::default();
+		for attr in attrs.iter().filter(|attr| attr.path().is_ident("orfile")) {
+			attr.parse_nested_meta(|meta| {
+				if meta.path.is_ident("merge") {
+					let value: LitStr = meta.value()?.parse()?;
+					options.append = match value.value().as_str() {
+						"append" => true,
+						"replace" => false,
+						_ => {
+							return Err(meta
+								.error("expected `merge = \"append\"` or `merge = \"replace\"`"))
+						}
+					};
+					Ok(())
+				} else if meta.path.is_ident("nested") {
+					options.nested = true;
+					Ok(())
+				} else if meta.path.is_ident("env") {
+					let value: LitStr = meta.value()?.parse()?;
+					options.env = Some(value.value());
+					Ok(())
+				} else if meta.path.is_ident("alias") {
+					let value: LitStr = meta.value()?.parse()?;
+					options.aliases.push(value.value());
+					Ok(())
+				} else if meta.path.is_ident("skip_env") {
+					options.skip_env = true;
+					Ok(())
+				} else if meta.path.is_ident("path") {
+					options.path = true;
+					Ok(())
+				} else {
+					Err(meta.error("unknown orfile field option"))
+				}
+			})
+			.unwrap_or_else(|e| abort!(e.span(), "{}", e));
+
+			if options.skip_env && options.env.is_some() {
+				abort!(attr, "`skip_env` cannot be combined with `env`");
+			}
+			if options.path && options.nested {
+				abort!(attr, "`path` cannot be combined with `nested`");
+			}
+		}
+		options
+	}
+}
+
+/// Finds the string value of `key` in the `#[serde(...)]` attributes.
+fn serde_value(attrs: &[Attribute], key: &str) -> Option<String> {
+	attrs
+		.iter()
+		.filter(|attr| attr.path().is_ident("serde"))
+		.filter_map(|attr| attr.parse_args_with(Punctuated::<Meta, Comma>::parse_terminated).ok())
+		.flatten()
+		.find_map(|meta| match meta {
+			Meta::NameValue(nv) if nv.path.is_ident(key) => match nv.value {
+				Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) => Some(s.value()),
+				_ => None,
+			},
+			_ => None,
+		})
+}
+
+/// Whether the `#[serde(...)]` attributes contain `key`, either bare or with a value.
+fn serde_flag(attrs: &[Attribute], key: &str) -> bool {
+	attrs
+		.iter()
+		.filter(|attr| attr.path().is_ident("serde"))
+		.filter_map(|attr| attr.parse_args_with(Punctuated::<Meta, Comma>::parse_terminated).ok())
+		.flatten()
+		.any(|meta| meta.path().is_ident(key))
+}
+
+/// Joins the `///` lines of the doc comment, if there is one.
+fn doc(attrs: &[Attribute]) -> Option<String> {
+	let lines: Vec<String> = attrs
+		.iter()
+		.filter(|attr| attr.path().is_ident("doc"))
+		.filter_map(|attr| match &attr.meta {
+			Meta::NameValue(nv) => match &nv.value {
+				Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) => Some(s.value().trim().to_string()),
+				_ => None,
+			},
+			_ => None,
+		})
+		.collect();
+	let doc = lines.join("\n").trim().to_string();
+	(!doc.is_empty()).then_some(doc)
+}
+
+/// Builds the expression producing a field's default as JSON, if it has one.
+///
+/// A serde default is preferred since it is what `using` deserializes with, and then a clap
+/// `default_value` or `default_value_t`.
+fn default(field: &syn::Field, default_all: bool) -> Option<TokenStream2> {
+	let ident = field.ident.as_ref().unwrap();
+	let ty = &field.ty;
+	let to_value = |expr: TokenStream2| {
+		quote! { orfile::serde_json::to_value(#expr).unwrap_or_default() }
+	};
+
+	let serde_metas: Vec<Meta> = field
+		.attrs
+		.iter()
+		.filter(|attr| attr.path().is_ident("serde"))
+		.filter_map(|attr| attr.parse_args_with(Punctuated::<Meta, Comma>::parse_terminated).ok())
+		.flatten()
+		.collect();
+	for meta in &serde_metas {
+		match meta {
+			Meta::Path(path) if path.is_ident("default") => {
+				return Some(to_value(quote! { <#ty as Default>::default() }));
+			}
+			Meta::NameValue(nv) if nv.path.is_ident("default") => {
+				if let Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) = &nv.value {
+					let path: syn::Path = s.parse().unwrap_or_else(|e| abort!(s, "{}", e));
+					return Some(to_value(quote! { #path() }));
+				}
+			}
+			_ => {}
+		}
+	}
+	if default_all {
+		return Some(to_value(quote! { <Self as Default>::default().#ident }));
+	}
+
+	let clap_metas: Vec<Meta> = field
+		.attrs
+		.iter()
+		.filter(|attr| attr.path().is_ident("clap") || attr.path().is_ident("arg"))
+		.filter_map(|attr| attr.parse_args_with(Punctuated::<Meta, Comma>::parse_terminated).ok())
+		.flatten()
+		.collect();
+	for meta in &clap_metas {
+		match meta {
+			Meta::NameValue(nv) if nv.path.is_ident("default_value") => {
+				if let Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) = &nv.value {
+					// Parsed like a value from the command line
+					return Some(quote! { orfile::coerce::infer(#s) });
+				}
+			}
+			Meta::NameValue(nv) if nv.path.is_ident("default_value_t") => {
+				let value = &nv.value;
+				return Some(to_value(quote! { #value }));
+			}
+			Meta::Path(path) if path.is_ident("default_value_t") => {
+				return Some(to_value(quote! { <#ty as Default>::default() }));
+			}
+			_ => {}
+		}
+	}
+	None
+}
+
+/// Applies a serde `rename_all` rule to a field name.
+fn rename(name: &str, rule: &str) -> String {
+	match rule {
+		"lowercase" => name.to_lowercase(),
+		"UPPERCASE" => name.to_uppercase(),
+		"PascalCase" => name.to_upper_camel_case(),
+		"camelCase" => name.to_lower_camel_case(),
+		"snake_case" => name.to_snake_case(),
+		"SCREAMING_SNAKE_CASE" => name.to_shouty_snake_case(),
+		"kebab-case" => name.to_kebab_case(),
+		"SCREAMING-KEBAB-CASE" => name.to_shouty_kebab_case(),
+		_ => name.to_string(),
+	}
+}
+
+/// Unwraps `Option<T>`, `Vec<T>` and `Box<T>` to reach the config type of a nested field.
+fn nested_type(ty: &Type) -> &Type {
+	if let Type::Path(path) = ty {
+		if let Some(segment) = path.path.segments.last() {
+			if ["Option", "Vec", "Box"].iter().any(|wrapper| segment.ident == wrapper) {
+				if let PathArguments::AngleBracketed(args) = &segment.arguments {
+					if let Some(GenericArgument::Type(inner)) = args.args.first() {
+						return nested_type(inner);
+					}
+				}
+			}
+		}
+	}
+	ty
+}
+
+/// The path of the [orfile::Kind] matching a field type.
+///
+/// Option, collection and box wrappers are followed to the item type, which is an object when the
+/// field is nested and otherwise recognized by name. Unrecognized types accept any value.
+fn kind(ty: &Type, nested: bool) -> TokenStream2 {
+	let Type::Path(path) = ty else {
+		return quote! { orfile::Kind::Any };
+	};
+	let Some(segment) = path.path.segments.last() else {
+		return quote! { orfile::Kind::Any };
+	};
+	let inner = match &segment.arguments {
+		PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+			GenericArgument::Type(inner) => Some(inner),
+			_ => None,
+		}),
+		_ => None,
+	};
+
+	match (segment.ident.to_string().as_str(), inner) {
+		("Option", Some(inner)) => {
+			let inner = kind(inner, nested);
+			quote! { orfile::Kind::Option(Box::new(#inner)) }
+		}
+		("Vec" | "VecDeque" | "HashSet" | "BTreeSet", Some(inner)) => {
+			let inner = kind(inner, nested);
+			quote! { orfile::Kind::Array(Box::new(#inner)) }
+		}
+		("Box", Some(inner)) => kind(inner, nested),
+		_ if nested => quote! { orfile::Kind::Object },
+		("bool", _) => quote! { orfile::Kind::Boolean },
+		("i8" | "i16" | "i32" | "i64" | "i128" | "isize", _) => {
+			quote! { orfile::Kind::Integer { unsigned: false } }
+		}
+		("u8" | "u16" | "u32" | "u64" | "u128" | "usize", _) => {
+			quote! { orfile::Kind::Integer { unsigned: true } }
+		}
+		("f32" | "f64", _) => quote! { orfile::Kind::Number },
+		("String" | "char" | "PathBuf", _) => quote! { orfile::Kind::String },
+		("HashMap" | "BTreeMap", _) => quote! { orfile::Kind::Object },
+		_ => quote! { orfile::Kind::Any },
+	}
+}
+
+/// Whether a type is `Option<T>`, which serde fills with `None` when the key is missing.
+fn is_option(ty: &Type) -> bool {
+	matches!(ty, Type::Path(path) if path.path.segments.last().is_some_and(|s| s.ident == "Option"))
+}
+
+pub fn impl_orfile_config(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	let struct_name = &input.ident;
+	let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+	let rename_all = serde_value(&input.attrs, "rename_all");
+	let default_all = serde_flag(&input.attrs, "default");
+
+	let fields = match &input.data {
+		Data::Struct(data) => match &data.fields {
+			Fields::Named(fields) => &fields.named,
+			_ => abort!(
+				struct_name,
+				"OrfileConfig can only be derived for structs with named fields"
+			),
+		},
+		_ => abort!(struct_name, "OrfileConfig can only be derived for structs"),
+	};
+
+	let field_defs: Vec<TokenStream2> = fields
+		.iter()
+		.map(|f| {
+			// The keys of a flattened field sit beside the others, so its type's fields are spliced in
+			if serde_flag(&f.attrs, "flatten") {
+				let ty = nested_type(&f.ty);
+				return if is_option(&f.ty) {
+					quote! {
+						<#ty as orfile::OrfileConfig>::fields()
+							.into_iter()
+							.map(|field| orfile::Field { required: false, ..field })
+							.collect::<Vec<_>>()
+					}
+				} else {
+					quote! { <#ty as orfile::OrfileConfig>::fields() }
+				};
+			}
+
+			let ident = f.ident.as_ref().unwrap().to_string();
+			let ident = ident.trim_start_matches("r#");
+			let name = serde_value(&f.attrs, "rename").unwrap_or_else(|| match &rename_all {
+				Some(rule) => rename(ident, rule),
+				None => ident.to_string(),
+			});
+			let options = FieldOptions::parse(&f.attrs);
+
+			let merge = options.append.then(|| {
+				quote! { .with_merge(orfile::MergeStrategy::Append) }
+			});
+			let nested = options.nested.then(|| {
+				let ty = nested_type(&f.ty);
+				quote! { .with_nested(<#ty as orfile::OrfileConfig>::fields()) }
+			});
+			let env = options.env.map(|env| quote! { .with_env(#env) });
+			let aliases = options.aliases.iter().map(|alias| quote! { .with_alias(#alias) });
+			let skip_env = options.skip_env.then(|| quote! { .skip_env() });
+			let path = options.path.then(|| quote! { .path() });
+
+			let kind = kind(&f.ty, options.nested);
+			let required = !(default_all
+				|| is_option(&f.ty)
+				|| ["default", "skip", "skip_deserializing"]
+					.iter()
+					.any(|key| serde_flag(&f.attrs, key)));
+			let required = required.then(|| quote! { .required() });
+			let description = doc(&f.attrs).map(|doc| quote! { .with_description(#doc) });
+			let default =
+				default(f, default_all).map(|default| quote! { .with_default(|| #default) });
+
+			quote! {
+				vec![orfile::Field::new(#name)
+					#merge #nested #env #(#aliases)* #skip_env #path
+					.with_kind(#kind) #required #description #default]
+			}
+		})
+		.collect();
+
+	let description = doc(&input.attrs).map(|doc| {
+		quote! {
+			fn description() -> Option<&'static str> {
+				Some(#doc)
+			}
+		}
+	});
+
+	let expanded = quote! {
+		impl #impl_generics orfile::OrfileConfig for #struct_name #ty_generics #where_clause {
+			fn fields() -> Vec<orfile::Field> {
+				<[Vec<orfile::Field>]>::concat(&[#(#field_defs),*])
+			}
+
+			#description
+		}
+	};
+
+	TokenStream::from(expanded)
+}
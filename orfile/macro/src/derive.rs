@@ -1,16 +1,74 @@
+use heck::ToShoutySnakeCase;
 use proc_macro::TokenStream;
 use proc_macro2::Literal;
+use proc_macro_error::abort;
 use quote::{format_ident, quote};
-use syn::{parse_macro_input, Data, DeriveInput};
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, LitStr};
+
+/// The struct level `#[orfile(...)]` options.
+struct StructOptions {
+	/// The prefix of the environment variables read by `using`, or `None` when env loading is disabled.
+	env_prefix: Option<String>,
+	/// Whether `using` rejects merged keys which are not fields of the config type.
+	strict: bool,
+	/// The name config files are searched for under when no path is given, such as `tool/add`.
+	discover: Option<String>,
+}
+
+impl StructOptions {
+	fn parse(attrs: &[Attribute], struct_name: &str) -> Self {
+		// Defaults to the struct name in screaming snake case, e.g. `KEBAB_DIVIDE_` for `KebabDivide`
+		let mut env_prefix = Some(struct_name.to_shouty_snake_case());
+		let mut strict = false;
+		let mut discover = None;
+		for attr in attrs.iter().filter(|attr| attr.path().is_ident("orfile")) {
+			attr.parse_nested_meta(|meta| {
+				if meta.path.is_ident("env_prefix") {
+					let value: LitStr = meta.value()?.parse()?;
+					env_prefix = Some(value.value());
+					Ok(())
+				} else if meta.path.is_ident("no_env") {
+					env_prefix = None;
+					Ok(())
+				} else if meta.path.is_ident("strict") {
+					strict = true;
+					Ok(())
+				} else if meta.path.is_ident("discover") {
+					let value: LitStr = meta.value()?.parse()?;
+					discover = Some(value.value());
+					Ok(())
+				} else {
+					Err(meta.error("unknown orfile struct option"))
+				}
+			})
+			.unwrap_or_else(|e| abort!(e.span(), "{}", e));
+		}
+
+		let env_prefix =
+			env_prefix.map(
+				|prefix| {
+					if prefix.ends_with('_') {
+						prefix
+					} else {
+						format!("{}_", prefix)
+					}
+				},
+			);
+		Self { env_prefix, strict, discover }
+	}
+}
 
 pub fn impl_orfile(input: TokenStream) -> TokenStream {
 	let input = parse_macro_input!(input as DeriveInput);
 	let struct_name = &input.ident;
 	let vis = &input.vis;
-	let struct_prefix = struct_name.to_string().to_uppercase();
+	let options = StructOptions::parse(&input.attrs, &struct_name.to_string());
 
 	let mod_or_file = format_ident!("or_file");
 	let mod_using = format_ident!("using");
+	let mod_explain = format_ident!("explain");
+	let mod_schema = format_ident!("schema");
+	let mod_init = format_ident!("init");
 
 	let lower_case_struct_prefix = struct_name.to_string().to_lowercase();
 	let doc_where = Literal::string(&format!(
@@ -21,6 +79,28 @@ pub fn impl_orfile(input: TokenStream) -> TokenStream {
 		"Run {} with parameters from environment variables, config files, and CLI flags. See Orfile documentation for more details: <https://github.com/movementlabsxyz/orfile>",
 		lower_case_struct_prefix
 	));
+	let doc_explain = Literal::string(&format!(
+		"Print the config {} would run with under `using`, and where each value came from, without running it. See Orfile documentation for more details: <https://github.com/movementlabsxyz/orfile>",
+		lower_case_struct_prefix
+	));
+
+	let doc_schema = Literal::string(&format!(
+		"Print the JSON Schema of the config files {} reads under `using`. See Orfile documentation for more details: <https://github.com/movementlabsxyz/orfile>",
+		lower_case_struct_prefix
+	));
+
+	let doc_init = Literal::string(&format!(
+		"Write a starter config file for {} with defaults, placeholders for required fields and comments from the field docs. See Orfile documentation for more details: <https://github.com/movementlabsxyz/orfile>",
+		lower_case_struct_prefix
+	));
+
+	let doc_profile = Literal::string(&match &options.env_prefix {
+		Some(env_prefix) => format!(
+			"The profile to merge over the `default` section of config files with a `profiles` section, also read from {}PROFILE",
+			env_prefix
+		),
+		None => "The profile to merge over the `default` section of config files with a `profiles` section".to_string(),
+	});
 
 	let (config_fields, other_fields): (Vec<_>, Vec<_>) = match &input.data {
 		Data::Struct(data) => data.fields.iter().partition(|f| {
@@ -35,7 +115,17 @@ pub fn impl_orfile(input: TokenStream) -> TokenStream {
 	let config_idents: Vec<_> = config_fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
 	let config_path_idents: Vec<_> =
 		config_idents.iter().map(|id| format_ident!("{}_path", id)).collect();
+	let config_format_idents: Vec<_> =
+		config_idents.iter().map(|id| format_ident!("orfile_{}_format", id)).collect();
 	let config_types: Vec<_> = config_fields.iter().map(|f| &f.ty).collect();
+	// Calls on a probe use the config type's OrfileConfig if it derives one, see orfile::config::Probe
+	let config_probes: Vec<_> = config_types
+		.iter()
+		.map(|ty| quote! { (&orfile::config::Probe::<#ty>::new()) })
+		.collect();
+	let config_names: Vec<_> = config_idents.iter().map(|id| id.to_string()).collect();
+	let config_resolver_idents: Vec<_> =
+		config_idents.iter().map(|id| format_ident!("{}_resolver", id)).collect();
 
 	let other_field_defs: Vec<_> = other_fields
 		.iter()
@@ -56,82 +146,167 @@ pub fn impl_orfile(input: TokenStream) -> TokenStream {
 		})
 		.collect();
 
+	// The fields added to the `using` struct are prefixed with `orfile_` so they never clash with
+	// the struct's own fields, and so is their flag when a field of the struct already takes it
+	let other_names: Vec<String> = other_fields
+		.iter()
+		.filter_map(|f| f.ident.as_ref().map(ToString::to_string))
+		.collect();
+	let long = |name: &str| {
+		let flag = name.replace('_', "-");
+		let flag = if other_names.iter().any(|other| other == name) {
+			format!("orfile-{}", flag)
+		} else {
+			flag
+		};
+		Literal::string(&flag)
+	};
+	let long_validate_schema = long("validate_schema");
+	let long_profile = long("profile");
+	let long_save_resolved = long("save_resolved");
+
 	let config_path_fields: Vec<_> = config_path_idents
 		.iter()
-		.map(|id| {
+		.zip(config_format_idents.iter())
+		.zip(config_names.iter())
+		.map(|((id, format_id), config_name)| {
+			let long_format = long(&format!("{}_format", config_name));
 			let doc_path = Literal::string(&format!(
-				"Path to the config file for {}",
+				"Path to a config file for {}, repeatable with later files overriding earlier ones, or `-` to read standard input (format is detected from the extension: .json, .toml, .yaml, .yml)",
+				lower_case_struct_prefix
+			));
+			let doc_format = Literal::string(&format!(
+				"The format of the config files given for {}, overriding their extension (sniffed from the content for `-` by default)",
 				lower_case_struct_prefix
 			));
 
 			quote! {
 				#[doc = #doc_path]
 				#[clap(long)]
-				pub #id: Option<String>,
+				pub #id: Vec<String>,
+
+				#[doc = #doc_format]
+				#[clap(long = #long_format, value_name = "FORMAT", value_parser = ["json", "toml", "yaml"])]
+				pub #format_id: Option<String>,
 			}
 		})
 		.collect();
 
 	let env_and_file_mergers: Vec<_> = config_path_idents
 		.iter()
+		.zip(config_format_idents.iter())
 		.zip(config_types.iter())
-		.zip(config_idents.iter())
-		.map(|((path_ident, ty), config_ident)| {
-			let env_prefix = format!("{}_", struct_prefix);
-
-			quote! {
-				let mut config_map = serde_json::Map::new();
+		.zip(config_resolver_idents.iter())
+		.zip(config_names.iter())
+		.map(|((((path_ident, format_ident), ty), resolver_ident), config_name)| {
+			let strict = options.strict;
+			let probe = quote! { (&orfile::config::Probe::<#ty>::new()) };
+			let env_merger = options.env_prefix.as_ref().map(|env_prefix| {
+				quote! {
+					// Merge from ENV, with `__` separating nested keys
+					resolver.merge_env(#env_prefix);
+				}
+			});
 
-				// Merge from ENV
-				for (key, val) in std::env::vars() {
-					if let Some(suffix) = key.strip_prefix(#env_prefix) {
-						let field_name = suffix.to_ascii_lowercase().replace("__", "_");
-						config_map.insert(field_name, serde_json::Value::String(val));
+			// Several config fields are told apart by a `.<field>` suffix, as in `tool/add.args`
+			let discovered_paths = match &options.discover {
+				Some(name) => {
+					let name = if config_names.len() > 1 {
+						format!("{}.{}", name, config_name)
+					} else {
+						name.clone()
+					};
+					quote! {
+						orfile::discover::discover(#name)
+							.into_iter()
+							.map(|path| path.display().to_string())
+							.collect::<Vec<_>>()
 					}
 				}
+				None => quote! { Vec::<String>::new() },
+			};
 
-				// Merge from file
-				if let Some(file_path) = &self.#path_ident {
-					let file_contents = tokio::fs::read_to_string(file_path).await
-					.with_context(|| format!("Failed to read file at {}", file_path))?;
-					let file_value: serde_json::Value = serde_json::from_str(&file_contents)
-						.context("Failed to parse config file")?;
-
-					if let Some(map) = file_value.as_object() {
-						config_map.extend(map.clone());
+			let strict_check = options.strict.then(|| {
+				quote! {
+					// Reject keys which no config field knows, e.g. typos or stray env vars. Config types
+					// without OrfileConfig do not say which keys they know, so the check is left to serde
+					let known: Option<Vec<Vec<orfile::Field>>> =
+						[#(#config_probes.fields()),*].into_iter().collect();
+					if let Some(known) = known {
+						resolver.check_strict(&known.concat()).context("Unknown key in merged config")?;
 					}
 				}
+			});
+
+			quote! {
+				/// Merges the env, file and CLI layers for this config field without deserializing them.
+				pub async fn #resolver_ident(&self) -> Result<orfile::Resolver, Error> {
+					// Each layer is deep merged over the previous one so nested objects are only partly overridden
+					let mut resolver = orfile::Resolver::new(#probe.fields().unwrap_or_default());
+					resolver.set_profile(self.orfile_profile.clone());
 
-				// Merge from CLI extra args
-				for pair in self.extra_args.chunks(2) {
-					if pair.len() == 2 {
-						let key = pair[0]
-							.trim_start_matches("--")
-							.replace("-", "_")
-							.to_ascii_lowercase(); // optional for safety
-						let val = pair[1].to_string();
-
-						// Try to parse as different types in order of precedence
-						let value = if let Ok(obj) = serde_json::from_str::<serde_json::Value>(&val) {
-							obj
-						} else if let Ok(b) = val.parse::<bool>() {
-							serde_json::Value::Bool(b)
-						} else if let Ok(n) = val.parse::<f64>() {
-							serde_json::Value::Number(serde_json::Number::from_f64(n).unwrap())
-						} else {
-							serde_json::Value::String(val)
-						};
-
-						config_map.insert(key, value);
+					#env_merger
+
+					// Merge from files, each deep merged over the ones before it
+					// The format option only applies to the given files, discovered ones keep their extension
+					let (file_paths, format) = if self.#path_ident.is_empty() {
+						(#discovered_paths, None)
+					} else {
+						let format =
+							self.#format_ident.as_deref().and_then(orfile::Format::from_extension);
+						(self.#path_ident.clone(), format)
+					};
+					for file_path in &file_paths {
+						// Files named by `$extends` come first, so the file's own keys override them
+						let documents = orfile::extends::read_documents_as(file_path, format)
+							.await
+							.with_context(|| format!("Failed to load config file at {}", file_path))?;
+						for document in &documents {
+							if self.orfile_validate_schema {
+								let schema = #probe.schema(#strict).ok_or_else(|| {
+									orfile::anyhow::anyhow!(
+										"--validate-schema needs {} to derive OrfileConfig",
+										stringify!(#ty)
+									)
+								})?;
+								orfile::schema::validate_document(document, &schema)?;
+							}
+							resolver.merge_document(document);
+						}
 					}
-				}
+					resolver.check_profile()?;
+
+					// Merge from CLI extra args, with `.` separating nested keys and array indices
+					let assignments =
+						orfile::args::parse(&self.extra_args).context("Failed to parse extra args")?;
+					resolver.apply_args(assignments);
+
+					// Expand `${...}` references in file values once every layer is merged, then
+					// resolve path fields against the files which set them
+					resolver.interpolate()?;
+					resolver.resolve_paths();
 
-				let #config_ident: #ty = serde_json::from_value(serde_json::Value::Object(config_map))
-					.context("Failed to deserialize merged config")?;
+					#strict_check
+
+					Ok(resolver)
+				}
 			}
 		})
 		.collect();
 
+	let strict = options.strict;
+	let first_config_name = config_names.first().cloned().unwrap_or_default();
+	// Several config fields cannot share one file, so each needs its own path
+	let save_placeholder_check = (config_names.len() > 1).then(|| {
+		quote! {
+			if !path.contains("{field}") {
+				orfile::anyhow::bail!(
+					"--save-resolved needs a {{field}} placeholder in the path to save each config field to its own file"
+				);
+			}
+		}
+	});
+
 	let construct_config_fields: Vec<_> = config_idents.iter().map(|id| quote! { #id }).collect();
 	let construct_other_fields: Vec<_> = other_fields
 		.iter()
@@ -145,6 +320,7 @@ pub fn impl_orfile(input: TokenStream) -> TokenStream {
 		pub mod #mod_using {
 			use super::*;
 			use orfile::anyhow::{Context, Error};
+			use orfile::config::{Derived as _, Underived as _};
 			use orfile::serde_json;
 
 			#[derive(clap::Parser, Debug, Clone)]
@@ -152,6 +328,18 @@ pub fn impl_orfile(input: TokenStream) -> TokenStream {
 			pub struct #struct_name {
 				#(#config_path_fields)*
 
+				/// Validate config files against the JSON Schema of their config type before merging them
+				#[clap(long = #long_validate_schema)]
+				pub orfile_validate_schema: bool,
+
+				#[doc = #doc_profile]
+				#[clap(long = #long_profile, value_name = "PROFILE")]
+				pub orfile_profile: Option<String>,
+
+				/// Write the resolved config to this path, in the format matching its extension, so the run can be replayed from it
+				#[clap(long = #long_save_resolved, value_name = "PATH")]
+				pub orfile_save_resolved: Option<String>,
+
 				#(#other_field_defs)*
 
 				/// Extra arguments to be passed to the CLI
@@ -159,13 +347,238 @@ pub fn impl_orfile(input: TokenStream) -> TokenStream {
 			}
 
 			impl #struct_name {
+				#(#env_and_file_mergers)*
+
+				/// Writes a resolved config to `--save-resolved`, if given.
+				async fn save_resolved(&self, field: &str, config: serde_json::Value) -> Result<(), Error> {
+					let Some(path) = &self.orfile_save_resolved else {
+						return Ok(());
+					};
+					#save_placeholder_check
+					let path = path.replace("{field}", field);
+					orfile::write_file(&path, &config)
+						.await
+						.with_context(|| format!("Failed to save resolved config at {}", path))
+				}
+
 				pub async fn resolve(self) -> Result<super::#struct_name, Error> {
-					#(#env_and_file_mergers)*
+					self.resolve_with_provenance().await.map(|(resolved, _)| resolved)
+				}
+
+				/// Resolves the struct along with the source of each config value, keyed by paths such as `args.left`.
+				pub async fn resolve_with_provenance(
+					self,
+				) -> Result<(super::#struct_name, orfile::Provenance), Error> {
+					let mut provenance = orfile::Provenance::default();
+
+					#(
+						// Env and CLI values are kept as strings and coerced to the field types here
+						let (#config_idents, config_provenance) = self
+							.#config_resolver_idents()
+							.await?
+							.finish::<#config_types>()
+							.context("Failed to deserialize merged config")?;
+						provenance.extend(config_provenance.prefixed(#config_names));
+					)*
+
+					// Only save once every config field has resolved
+					#(
+						self.save_resolved(#config_names, serde_json::to_value(&#config_idents)?).await?;
+					)*
 
-					Ok(super::#struct_name {
-						#(#construct_config_fields,)*
-						#(#construct_other_fields,)*
-					})
+					Ok((
+						super::#struct_name {
+							#(#construct_config_fields,)*
+							#(#construct_other_fields,)*
+						},
+						provenance,
+					))
+				}
+
+				/// Resolves the config values and pairs each with the layer it came from.
+				///
+				/// The values are those of the deserialized config, so env and CLI values show coerced to their field types.
+				pub async fn explain(self) -> Result<orfile::Explanation, Error> {
+					let mut explanation = orfile::Explanation::default();
+
+					#(
+						let (#config_idents, provenance) = self
+							.#config_resolver_idents()
+							.await?
+							.finish::<#config_types>()
+							.context("Failed to deserialize merged config")?;
+						let #config_idents = serde_json::to_value(&#config_idents)?;
+						let resolved = #config_idents.as_object().cloned().unwrap_or_default();
+						explanation.extend(
+							orfile::Explanation::new(&resolved, &provenance).prefixed(#config_names),
+						);
+					)*
+
+					Ok(explanation)
+				}
+
+				/// The `where` command line equivalent to this resolution, with the values from every layer passed as explicit flags.
+				///
+				/// The other fields of the struct are passed along as they were given on the command line.
+				pub async fn where_command(self) -> Result<Vec<String>, Error> {
+					self.where_command_from(std::env::args().collect()).await
+				}
+
+				/// Like [Self::where_command], reading the command line from `args` rather than the process arguments.
+				pub async fn where_command_from(self, args: Vec<String>) -> Result<Vec<String>, Error> {
+					// Parsing the `explain` struct accepts the arguments of both `using` and `explain`
+					let matches = <super::#mod_explain::#struct_name as clap::CommandFactory>::command()
+						.try_get_matches_from(orfile::command::resolving_args(&args))
+						.context("Failed to parse the command line")?;
+					// Built before the config values, which may shadow `args`
+					let mut command = orfile::command::where_prefix(args);
+					let mut config = serde_json::Map::new();
+
+					#(
+						let (#config_idents, _) = self
+							.#config_resolver_idents()
+							.await?
+							.finish::<#config_types>()
+							.context("Failed to deserialize merged config")?;
+						config.insert(#config_names.to_string(), serde_json::to_value(&#config_idents)?);
+					)*
+
+					command.extend(orfile::command::where_args(
+						&<super::#struct_name as clap::CommandFactory>::command(),
+						&serde_json::Value::Object(config),
+						&matches,
+					));
+					Ok(command)
+				}
+			}
+		}
+
+		pub mod #mod_explain {
+			use super::*;
+			use orfile::anyhow::{bail, Error};
+			use orfile::serde_json;
+
+			// The flattened `using` struct already defines the argument group named after the struct
+			#[derive(clap::Parser, Debug, Clone)]
+			#[group(skip)]
+			pub struct #struct_name {
+				/// How to print the resolved config, where `where` prints the equivalent `where` command line
+				#[clap(long, default_value = "table", value_parser = ["table", "json", "where"])]
+				pub output_format: String,
+
+				#[clap(flatten)]
+				pub using: #mod_using::#struct_name,
+			}
+
+			impl #struct_name {
+				/// Prints the resolved config and the source of each value.
+				pub async fn run(self) -> Result<(), Error> {
+					// Explaining a run only reads its config, it does not write any
+					if self.using.orfile_save_resolved.is_some() {
+						bail!("--save-resolved only applies to using, explain does not write files");
+					}
+					if self.output_format == "where" {
+						let command = self.using.where_command().await?;
+						println!("{}", orfile::command::shell_join(&command));
+						return Ok(());
+					}
+
+					let explanation = self.using.explain().await?;
+					match self.output_format.as_str() {
+						"json" => println!("{}", serde_json::to_string_pretty(&explanation.to_json())?),
+						_ => print!("{}", explanation.to_table()),
+					}
+					Ok(())
+				}
+			}
+		}
+
+		pub mod #mod_schema {
+			use super::*;
+			use orfile::anyhow::{anyhow, Error};
+			use orfile::config::{Derived as _, Underived as _};
+			use orfile::serde_json;
+
+			#[derive(clap::Parser, Debug, Clone)]
+			pub struct #struct_name {
+				/// The config field to print the schema of, defaulting to the first one
+				#[clap(long, value_parser = [#(#config_names),*])]
+				pub field: Option<String>,
+			}
+
+			impl #struct_name {
+				/// The JSON Schema of the selected config field's type.
+				pub fn schema(&self) -> Result<serde_json::Value, Error> {
+					match self.field.as_deref().unwrap_or(#first_config_name) {
+						#(#config_names => #config_probes.schema(#strict).ok_or_else(|| {
+							anyhow!("{} does not derive OrfileConfig", stringify!(#config_types))
+						}),)*
+						field => Err(anyhow!("No config field named {}", field)),
+					}
+				}
+
+				/// Prints the JSON Schema.
+				pub fn run(self) -> Result<(), Error> {
+					println!("{}", serde_json::to_string_pretty(&self.schema()?)?);
+					Ok(())
+				}
+			}
+		}
+
+		pub mod #mod_init {
+			use super::*;
+			use orfile::anyhow::{anyhow, bail, Context, Error};
+			use orfile::config::{Derived as _, Underived as _};
+
+			#[derive(clap::Parser, Debug, Clone)]
+			pub struct #struct_name {
+				/// Where to write the config file, printing it instead when not given
+				pub path: Option<String>,
+
+				/// The format of the config file, detected from the extension of the path by default
+				#[clap(long, value_parser = ["json", "toml", "yaml"])]
+				pub format: Option<String>,
+
+				/// The config field to write a config file for, defaulting to the first one
+				#[clap(long, value_parser = [#(#config_names),*])]
+				pub field: Option<String>,
+
+				/// Overwrite the file if it already exists
+				#[clap(long)]
+				pub force: bool,
+			}
+
+			impl #struct_name {
+				/// Renders the starter config file for the selected config field.
+				pub fn template(&self) -> Result<String, Error> {
+					let format = match (&self.format, &self.path) {
+						(Some(format), _) => orfile::Format::from_extension(format)
+							.ok_or_else(|| anyhow!("Unknown config file format {}", format))?,
+						(None, Some(path)) => orfile::Format::from_path(std::path::Path::new(path))?,
+						(None, None) => orfile::Format::Json,
+					};
+					match self.field.as_deref().unwrap_or(#first_config_name) {
+						#(#config_names => #config_probes.template(format).ok_or_else(|| {
+							anyhow!("{} does not derive OrfileConfig", stringify!(#config_types))
+						}),)*
+						field => Err(anyhow!("No config field named {}", field)),
+					}
+				}
+
+				/// Writes the starter config file, or prints it when no path was given.
+				pub fn run(self) -> Result<(), Error> {
+					let template = self.template()?;
+					match &self.path {
+						Some(path) => {
+							if !self.force && std::path::Path::new(path).exists() {
+								bail!("{} already exists, pass --force to overwrite it", path);
+							}
+							std::fs::write(path, template)
+								.with_context(|| format!("Failed to write config file at {}", path))?;
+						}
+						None => print!("{}", template),
+					}
+					Ok(())
 				}
 			}
 		}
@@ -174,6 +587,9 @@ pub fn impl_orfile(input: TokenStream) -> TokenStream {
 			use super::*;
 			use anyhow::Error;
 			use #mod_using;
+			use #mod_explain;
+			use #mod_schema;
+			use #mod_init;
 
 			#[derive(clap::Subcommand, Debug, Clone)]
 			#vis enum #struct_name {
@@ -182,13 +598,37 @@ pub fn impl_orfile(input: TokenStream) -> TokenStream {
 
 				#[doc = #doc_using]
 				Using(#mod_using::#struct_name),
+
+				#[doc = #doc_explain]
+				Explain(#mod_explain::#struct_name),
+
+				#[doc = #doc_schema]
+				Schema(#mod_schema::#struct_name),
+
+				#[doc = #doc_init]
+				Init(#mod_init::#struct_name),
 			}
 
 			impl #struct_name {
-				pub async fn resolve(self) -> Result<super::#struct_name, Error> {
+				/// Resolves the struct to run.
+				///
+				/// `explain`, `schema` and `init` print or write their output instead and return `None`, leaving it to the caller to exit.
+				pub async fn resolve(self) -> Result<Option<super::#struct_name>, Error> {
+					Ok(self.resolve_with_provenance().await?.map(|(resolved, _)| resolved))
+				}
+
+				/// Resolves the struct along with the source of each config value, or `None` as [Self::resolve] does.
+				///
+				/// Every value passed to `where` is an explicit flag, so its provenance is empty.
+				pub async fn resolve_with_provenance(
+					self,
+				) -> Result<Option<(super::#struct_name, orfile::Provenance)>, Error> {
 					match self {
-						Self::Where(inner) => Ok(inner),
-						Self::Using(inner) => inner.resolve().await,
+						Self::Where(inner) => Ok(Some((inner, orfile::Provenance::default()))),
+						Self::Using(inner) => inner.resolve_with_provenance().await.map(Some),
+						Self::Explain(inner) => inner.run().await.map(|()| None),
+						Self::Schema(inner) => inner.run().map(|()| None),
+						Self::Init(inner) => inner.run().map(|()| None),
 					}
 				}
 			}
@@ -1,6 +1,7 @@
 use proc_macro::TokenStream;
 use proc_macro_error::proc_macro_error;
 
+mod config;
 mod derive;
 
 #[proc_macro_derive(Orfile, attributes(orfile))]
@@ -8,3 +9,9 @@ mod derive;
 pub fn orfile_derive(input: TokenStream) -> TokenStream {
 	derive::impl_orfile(input)
 }
+
+#[proc_macro_derive(OrfileConfig, attributes(orfile))]
+#[proc_macro_error]
+pub fn orfile_config_derive(input: TokenStream) -> TokenStream {
+	config::impl_orfile_config(input)
+}
@@ -1,4 +1,4 @@
-pub use orfile_macro::*;
-// pub use orfile_util::*;
 pub use anyhow;
+pub use orfile_macro::*;
+pub use orfile_util::*;
 pub use serde_json;
@@ -0,0 +1,228 @@
+use crate::config::{self, Field, Kind, MergeStrategy};
+use crate::path::{KeyPath, Segment};
+use crate::Error;
+use serde_json::{Map, Value};
+
+/// A single `--key value` assignment from `using` extra args.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment {
+	/// Where the value goes in the config.
+	pub path: KeyPath,
+	/// The raw value, coerced to the field type on deserialization.
+	pub value: Value,
+	/// The flag as written, such as `--db.port`.
+	pub flag: String,
+	/// The 1-based position of the flag among the extra args.
+	pub position: usize,
+}
+
+/// Normalizes a flag name into a config path.
+///
+/// The leading dashes are dropped, dashes become underscores and dots separate nested keys, so
+/// `--db.max-connections` targets `db.max_connections` and `--peers.0.url` targets the `url` of the
+/// first peer.
+pub fn key_path(flag: &str) -> KeyPath {
+	KeyPath::parse_dotted(&flag.trim_start_matches("--").replace('-', "_").to_ascii_lowercase())
+}
+
+/// Parses extra args into assignments.
+///
+/// Each arg is one of:
+/// - `--key value`, where the value is the next arg unless it starts with `--`;
+/// - `--key=value`, which also allows values starting with `--`;
+/// - `--key` with no value, meaning `true`;
+/// - `--no-key` with no value, meaning `false`.
+///
+/// Anything else, such as a stray value or an empty key, is an error rather than being skipped.
+pub fn parse(extra_args: &[String]) -> Result<Vec<Assignment>, Error> {
+	let mut assignments = Vec::new();
+	let mut args = extra_args.iter().enumerate().peekable();
+
+	while let Some((index, arg)) = args.next() {
+		let invalid = |reason: &str| Error::InvalidArg {
+			position: index + 1,
+			arg: arg.clone(),
+			reason: reason.to_string(),
+		};
+
+		let Some(flag) = arg.strip_prefix("--") else {
+			return Err(invalid("expected a `--key` before this value"));
+		};
+
+		let (key, value) = match flag.split_once('=') {
+			Some((flag, value)) => (flag, Value::String(value.to_string())),
+			None => match args.next_if(|(_, next)| !next.starts_with("--")) {
+				Some((_, value)) => (flag, Value::String(value.clone())),
+				None => match flag.strip_prefix("no-") {
+					Some(negated) => (negated, Value::Bool(false)),
+					None => (flag, Value::Bool(true)),
+				},
+			},
+		};
+
+		let path = key_path(key);
+		if path.is_empty() {
+			return Err(invalid("the key is empty"));
+		}
+		let flag = format!("--{}", flag.split_once('=').map_or(flag, |(flag, _)| flag));
+		assignments.push(Assignment { path, value, flag, position: index + 1 });
+	}
+
+	Ok(assignments)
+}
+
+/// Applies assignments over a merged config.
+///
+/// Each assignment only replaces the leaf it names, so `--peers.0.url` keeps the other fields of
+/// the first peer from the lower layers. An array field given more than once, as in
+/// `--peers a --peers b`, collects its values into an array which then replaces or extends the
+/// lower layers according to the merge strategy of the field. Other fields take their last value.
+pub fn apply(map: &mut Map<String, Value>, assignments: Vec<Assignment>, fields: &[Field]) {
+	for (path, value, _) in group(assignments, fields) {
+		path.insert_with(map, value, fields);
+	}
+}
+
+/// Groups assignments by path, collecting repeated keys of array fields into arrays.
+///
+/// A repeated key of any other field keeps its last value. Returns each path with its value and
+/// the assignments that produced it, in order of first use.
+pub fn group(
+	assignments: Vec<Assignment>,
+	fields: &[Field],
+) -> Vec<(KeyPath, Value, Vec<Assignment>)> {
+	let mut grouped: Vec<(KeyPath, Vec<Assignment>)> = Vec::new();
+	for assignment in assignments {
+		match grouped.iter_mut().find(|(path, _)| *path == assignment.path) {
+			Some((_, group)) => group.push(assignment),
+			None => grouped.push((assignment.path.clone(), vec![assignment])),
+		}
+	}
+
+	grouped
+		.into_iter()
+		.map(|(path, mut group)| {
+			let value = match group.as_slice() {
+				[assignment] => assignment.value.clone(),
+				group if collects(&path, fields) => {
+					Value::Array(group.iter().map(|a| a.value.clone()).collect())
+				}
+				_ => {
+					group.drain(..group.len() - 1);
+					group[0].value.clone()
+				}
+			};
+			(path, value, group)
+		})
+		.collect()
+}
+
+/// Whether a repeated key at `path` collects its values into an array.
+///
+/// That is the case for array fields, fields which append across layers, and keys whose type is
+/// unknown, such as those of fields which are not `#[orfile(nested)]`.
+fn collects(path: &KeyPath, fields: &[Field]) -> bool {
+	fn is_array(kind: &Kind) -> bool {
+		match kind {
+			Kind::Array(_) | Kind::Any => true,
+			Kind::Option(inner) => is_array(inner),
+			_ => false,
+		}
+	}
+
+	// A repeated array item, as in `--peers.0 a --peers.0 b`, is a single value
+	if matches!(path.0.last(), Some(Segment::Index(_))) {
+		return false;
+	}
+	let keys = path.0.iter().filter(|segment| matches!(segment, Segment::Key(_))).count();
+	let chain = config::fields_along(fields, path);
+	match chain.last() {
+		Some(field) if chain.len() == keys => {
+			field.merge == MergeStrategy::Append || is_array(&field.kind)
+		}
+		_ => true,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn test_apply_dotted_args() {
+		let extra_args =
+			["--db.port", "6543", "--peers.0.max-weight", "2", "--left", "1"].map(String::from);
+		let mut map =
+			json!({ "db": { "host": "localhost", "port": 5432 }, "peers": [{ "url": "a" }] })
+				.as_object()
+				.unwrap()
+				.clone();
+
+		apply(&mut map, parse(&extra_args).unwrap(), &[]);
+		assert_eq!(
+			Value::Object(map),
+			json!({
+				"left": "1",
+				"db": { "host": "localhost", "port": "6543" },
+				"peers": [{ "url": "a", "max_weight": "2" }],
+			})
+		);
+	}
+
+	#[test]
+	fn test_parse_flag_forms() {
+		let extra_args = ["--verbose", "--left=1", "--no-color", "--right", "-4", "--name=--x"]
+			.map(String::from);
+		let mut map = Map::new();
+
+		apply(&mut map, parse(&extra_args).unwrap(), &[]);
+		assert_eq!(
+			Value::Object(map),
+			json!({ "verbose": true, "left": "1", "color": false, "right": "-4", "name": "--x" })
+		);
+	}
+
+	#[test]
+	fn test_apply_repeated_keys() {
+		let fields = vec![Field::new("peers").with_merge(MergeStrategy::Append)];
+		let extra_args =
+			["--peers", "c", "--peers", "d", "--tags", "x", "--tags", "y"].map(String::from);
+		let mut map = json!({ "peers": ["a", "b"], "tags": ["w"] }).as_object().unwrap().clone();
+
+		apply(&mut map, parse(&extra_args).unwrap(), &fields);
+		assert_eq!(
+			Value::Object(map),
+			json!({ "peers": ["a", "b", "c", "d"], "tags": ["x", "y"] })
+		);
+	}
+
+	#[test]
+	fn test_apply_repeated_scalar_keeps_last() {
+		let fields = vec![
+			Field::new("right").with_kind(Kind::Integer { unsigned: true }),
+			Field::new("peers").with_kind(Kind::Array(Box::new(Kind::String))),
+		];
+		let extra_args =
+			["--right", "4", "--right", "5", "--peers.0", "a", "--peers.0", "b"].map(String::from);
+		let mut map = json!({ "peers": ["x"] }).as_object().unwrap().clone();
+
+		let grouped = group(parse(&extra_args).unwrap(), &fields);
+		assert_eq!(grouped[0].2.iter().map(|a| a.position).collect::<Vec<_>>(), vec![3]);
+
+		apply(&mut map, parse(&extra_args).unwrap(), &fields);
+		assert_eq!(Value::Object(map), json!({ "right": "5", "peers": ["b"] }));
+	}
+
+	#[test]
+	fn test_parse_rejects_malformed_args() {
+		let err = parse(&["--left", "1", "2"].map(String::from)).unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"invalid extra arg `2` at position 3: expected a `--key` before this value"
+		);
+
+		let err = parse(&["--=1"].map(String::from)).unwrap_err();
+		assert_eq!(err.to_string(), "invalid extra arg `--=1` at position 1: the key is empty");
+	}
+}
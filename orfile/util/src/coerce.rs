@@ -0,0 +1,365 @@
+use serde::de::value::{MapDeserializer, SeqDeserializer, StringDeserializer};
+use serde::de::{DeserializeOwned, Deserializer, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use serde_json::{Map, Number, Value};
+
+/// Infers a JSON value from a raw string.
+///
+/// The string is tried as JSON, then as a bool, then as a number, and is otherwise kept as a string.
+/// Numbers are read as `i64` or `u64` first and only become floats when they have a fractional part
+/// or an exponent. Integers outside the `i64`/`u64` range and non-finite floats stay strings, so no
+/// precision is lost before a typed field parses them.
+pub fn infer(raw: &str) -> Value {
+	match serde_json::from_str::<Value>(raw) {
+		// JSON reads out-of-range integers as floats, so numbers go through [infer_number].
+		Ok(Value::Number(_)) | Err(_) => {}
+		Ok(value) => return value,
+	}
+
+	if let Ok(b) = raw.parse::<bool>() {
+		Value::Bool(b)
+	} else if let Some(n) = infer_number(raw) {
+		Value::Number(n)
+	} else {
+		Value::String(raw.to_string())
+	}
+}
+
+/// Parses an integer losslessly, or a finite float when the string has a fraction or exponent.
+fn infer_number(raw: &str) -> Option<Number> {
+	if let Ok(i) = raw.parse::<i64>() {
+		Some(Number::from(i))
+	} else if let Ok(u) = raw.parse::<u64>() {
+		Some(Number::from(u))
+	} else if raw.contains(['.', 'e', 'E']) {
+		raw.parse::<f64>().ok().and_then(Number::from_f64)
+	} else {
+		None
+	}
+}
+
+/// Reads a raw string as a list.
+///
+/// A JSON array is used as is. Anything else is split on commas, so `a, b` yields `["a", "b"]` and an
+/// empty string yields an empty list.
+pub fn split_list(raw: &str) -> Vec<Value> {
+	match serde_json::from_str::<Value>(raw) {
+		Ok(Value::Array(items)) => items,
+		_ if raw.trim().is_empty() => Vec::new(),
+		_ => raw.split(',').map(|item| Value::String(item.trim().to_string())).collect(),
+	}
+}
+
+/// Implements numeric and bool deserialization by parsing strings directly as the target type.
+///
+/// Parsing with the target type keeps integers beyond `i64`/`u64` lossless and lets float fields
+/// accept `NaN` and `inf`. Anything that does not parse falls back to [infer].
+macro_rules! deserialize_parsed {
+	($($method:ident => $ty:ty, $visit:ident;)*) => {
+		$(
+			fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+				if let Value::String(s) = &self.0 {
+					if let Ok(parsed) = s.trim().parse::<$ty>() {
+						return visitor.$visit(parsed);
+					}
+				}
+				self.deserialize_inferred(visitor)
+			}
+		)*
+	};
+}
+
+/// Deserializes a merged config, coercing string values to the types requested by `T`.
+///
+/// Values from the environment and the command line arrive as strings. A string is only
+/// reinterpreted with [infer] when the target field asks for something other than a string, so
+/// a `String` field holding `"123"` stays a string while a `u64` field receives `123`.
+///
+/// Fields under `#[serde(flatten)]` and untagged enums are buffered by serde without asking for a
+/// type, so their strings are passed through as strings and only typed values, such as those from
+/// config files, reach non-string fields there.
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, serde_json::Error> {
+	T::deserialize(Coerce(value))
+}
+
+/// A [Deserializer] over a JSON value which coerces strings on demand.
+struct Coerce(Value);
+
+impl Coerce {
+	/// Replaces a string with its inferred value and deserializes whatever results.
+	fn deserialize_inferred<'de, V: Visitor<'de>>(
+		self,
+		visitor: V,
+	) -> Result<V::Value, serde_json::Error> {
+		match self.0 {
+			Value::String(s) => match infer(&s) {
+				Value::String(s) => visitor.visit_string(s),
+				value => Coerce(value).deserialize_any(visitor),
+			},
+			value => Coerce(value).deserialize_any(visitor),
+		}
+	}
+}
+
+fn visit_array<'de, V: Visitor<'de>>(
+	array: Vec<Value>,
+	visitor: V,
+) -> Result<V::Value, serde_json::Error> {
+	let mut seq = SeqDeserializer::new(array.into_iter().map(Coerce));
+	let value = visitor.visit_seq(&mut seq)?;
+	seq.end()?;
+	Ok(value)
+}
+
+fn visit_object<'de, V: Visitor<'de>>(
+	object: Map<String, Value>,
+	visitor: V,
+) -> Result<V::Value, serde_json::Error> {
+	let mut map = MapDeserializer::new(object.into_iter().map(|(k, v)| (k, Coerce(v))));
+	let value = visitor.visit_map(&mut map)?;
+	map.end()?;
+	Ok(value)
+}
+
+impl<'de> IntoDeserializer<'de, serde_json::Error> for Coerce {
+	type Deserializer = Self;
+
+	fn into_deserializer(self) -> Self::Deserializer {
+		self
+	}
+}
+
+impl<'de> Deserializer<'de> for Coerce {
+	type Error = serde_json::Error;
+
+	fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		match self.0 {
+			Value::String(s) => visitor.visit_string(s),
+			Value::Array(array) => visit_array(array, visitor),
+			Value::Object(object) => visit_object(object, visitor),
+			value => value.deserialize_any(visitor),
+		}
+	}
+
+	deserialize_parsed! {
+		deserialize_bool => bool, visit_bool;
+		deserialize_i8 => i8, visit_i8;
+		deserialize_i16 => i16, visit_i16;
+		deserialize_i32 => i32, visit_i32;
+		deserialize_i64 => i64, visit_i64;
+		deserialize_i128 => i128, visit_i128;
+		deserialize_u8 => u8, visit_u8;
+		deserialize_u16 => u16, visit_u16;
+		deserialize_u32 => u32, visit_u32;
+		deserialize_u64 => u64, visit_u64;
+		deserialize_u128 => u128, visit_u128;
+		deserialize_f32 => f32, visit_f32;
+		deserialize_f64 => f64, visit_f64;
+	}
+
+	fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		self.deserialize_string(visitor)
+	}
+
+	fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		self.deserialize_string(visitor)
+	}
+
+	fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		match self.0 {
+			Value::String(s) => visitor.visit_string(s),
+			value => value.deserialize_string(visitor),
+		}
+	}
+
+	fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		match self.0 {
+			Value::Null => visitor.visit_none(),
+			value => visitor.visit_some(Coerce(value)),
+		}
+	}
+
+	fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		self.deserialize_inferred(visitor)
+	}
+
+	fn deserialize_unit_struct<V: Visitor<'de>>(
+		self,
+		_name: &'static str,
+		visitor: V,
+	) -> Result<V::Value, Self::Error> {
+		self.deserialize_inferred(visitor)
+	}
+
+	fn deserialize_newtype_struct<V: Visitor<'de>>(
+		self,
+		_name: &'static str,
+		visitor: V,
+	) -> Result<V::Value, Self::Error> {
+		visitor.visit_newtype_struct(self)
+	}
+
+	fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		match self.0 {
+			Value::String(s) => visit_array(split_list(&s), visitor),
+			value => Coerce(value).deserialize_any(visitor),
+		}
+	}
+
+	fn deserialize_tuple<V: Visitor<'de>>(
+		self,
+		_len: usize,
+		visitor: V,
+	) -> Result<V::Value, Self::Error> {
+		self.deserialize_inferred(visitor)
+	}
+
+	fn deserialize_tuple_struct<V: Visitor<'de>>(
+		self,
+		_name: &'static str,
+		_len: usize,
+		visitor: V,
+	) -> Result<V::Value, Self::Error> {
+		self.deserialize_inferred(visitor)
+	}
+
+	fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		self.deserialize_inferred(visitor)
+	}
+
+	fn deserialize_struct<V: Visitor<'de>>(
+		self,
+		_name: &'static str,
+		_fields: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, Self::Error> {
+		self.deserialize_inferred(visitor)
+	}
+
+	fn deserialize_enum<V: Visitor<'de>>(
+		self,
+		name: &'static str,
+		variants: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, Self::Error> {
+		match self.0 {
+			Value::String(s) => {
+				let variant: StringDeserializer<Self::Error> = s.into_deserializer();
+				visitor.visit_enum(variant)
+			}
+			value => value.deserialize_enum(name, variants, visitor),
+		}
+	}
+
+	fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+		visitor.visit_unit()
+	}
+
+	forward_to_deserialize_any! {
+		bytes byte_buf identifier
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde::Deserialize;
+	use serde_json::json;
+
+	#[derive(Debug, PartialEq, Deserialize)]
+	struct Args {
+		left: u64,
+		name: String,
+		verbose: bool,
+		peers: Vec<u16>,
+		ratio: Option<f64>,
+	}
+
+	#[test]
+	fn test_coerce_lists() {
+		assert_eq!(from_value::<Vec<u16>>(json!("1, 2")).unwrap(), vec![1, 2]);
+		assert_eq!(from_value::<Vec<String>>(json!("[\"a,b\"]")).unwrap(), vec!["a,b"]);
+		assert_eq!(from_value::<Vec<String>>(json!("a")).unwrap(), vec!["a"]);
+		assert!(from_value::<Vec<String>>(json!("")).unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_coerce_by_target_type() {
+		let value = json!({
+			"left": "1",
+			"name": "123",
+			"verbose": "true",
+			"peers": "[1, 2]",
+			"ratio": "0.5",
+		});
+
+		let args: Args = from_value(value).unwrap();
+		assert_eq!(
+			args,
+			Args {
+				left: 1,
+				name: "123".to_string(),
+				verbose: true,
+				peers: vec![1, 2],
+				ratio: Some(0.5),
+			}
+		);
+	}
+
+	#[test]
+	fn test_infer_numbers() {
+		assert_eq!(infer("4"), json!(4));
+		assert_eq!(infer("+4"), json!(4));
+		assert_eq!(infer("18446744073709551615"), json!(u64::MAX));
+		assert_eq!(infer("4.5"), json!(4.5));
+		assert_eq!(infer("1e3"), json!(1000.0));
+		assert_eq!(
+			infer("340282366920938463463374607431768211455"),
+			json!("340282366920938463463374607431768211455")
+		);
+		assert_eq!(infer("NaN"), json!("NaN"));
+		assert_eq!(infer("inf"), json!("inf"));
+	}
+
+	#[test]
+	fn test_coerce_lossless_numbers() {
+		assert_eq!(from_value::<u64>(json!("4")).unwrap(), 4);
+		assert_eq!(from_value::<u128>(json!(u128::MAX.to_string())).unwrap(), u128::MAX);
+		assert_eq!(
+			from_value::<i128>(json!("-170141183460469231731687303715884105728")).unwrap(),
+			i128::MIN
+		);
+		assert!(from_value::<f64>(json!("NaN")).unwrap().is_nan());
+		assert_eq!(from_value::<f64>(json!("-inf")).unwrap(), f64::NEG_INFINITY);
+		assert!(from_value::<u8>(json!("256")).is_err());
+	}
+
+	#[test]
+	fn test_coerce_rejects_mismatched_string() {
+		let value = json!({ "left": "one", "name": "n", "verbose": true, "peers": [] });
+
+		let err = from_value::<Args>(value).unwrap_err();
+		assert!(err.to_string().contains("invalid type: string \"one\""), "{}", err);
+	}
+
+	#[test]
+	fn test_flattened_fields_are_not_coerced() {
+		#[derive(Debug, PartialEq, Deserialize)]
+		struct Flattened {
+			name: String,
+			#[serde(flatten)]
+			limits: Limits,
+		}
+
+		#[derive(Debug, PartialEq, Deserialize)]
+		struct Limits {
+			max: u64,
+		}
+
+		let flattened: Flattened = from_value(json!({ "name": "123", "max": 5 })).unwrap();
+		assert_eq!(flattened, Flattened { name: "123".to_string(), limits: Limits { max: 5 } });
+
+		let err = from_value::<Flattened>(json!({ "name": "123", "max": "5" })).unwrap_err();
+		assert!(err.to_string().contains("invalid type: string"), "{}", err);
+	}
+}
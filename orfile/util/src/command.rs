@@ -0,0 +1,239 @@
+use clap::parser::ValueSource;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use serde_json::{Map, Value};
+use std::path::Path;
+
+/// The subcommands after which the `using` parameters start.
+const RESOLVING_SUBCOMMANDS: &[&str] = &["using", "explain"];
+
+/// Builds the start of a `where` command line from the current invocation.
+///
+/// The program name and everything before `using` or `explain` are kept, so
+/// `tool add using --args-path a.json` yields `tool add where`. Without either subcommand only the
+/// program name is kept.
+pub fn where_prefix(args: impl IntoIterator<Item = String>) -> Vec<String> {
+	let mut args = args.into_iter();
+	let program = args
+		.next()
+		.map(|program| {
+			Path::new(&program)
+				.file_name()
+				.map(|name| name.to_string_lossy().into_owned())
+				.unwrap_or(program)
+		})
+		.unwrap_or_default();
+
+	let rest: Vec<String> = args.collect();
+	let mut prefix = vec![program];
+	if let Some(end) = rest.iter().position(|arg| RESOLVING_SUBCOMMANDS.contains(&arg.as_str())) {
+		prefix.extend(rest[..end].iter().cloned());
+	}
+	prefix.push("where".to_string());
+	prefix
+}
+
+/// The arguments of the current invocation from `using` or `explain` on, for parsing them again.
+///
+/// The subcommand itself comes first, in place of the program name. Without either subcommand
+/// only the program name is kept.
+pub fn resolving_args(args: &[String]) -> Vec<String> {
+	match args.iter().position(|arg| RESOLVING_SUBCOMMANDS.contains(&arg.as_str())) {
+		Some(start) => args[start..].to_vec(),
+		None => args.iter().take(1).cloned().collect(),
+	}
+}
+
+/// Turns resolved config values into the flags of the `where` command.
+///
+/// Each argument of `command` takes the value found under its id, at any depth of `config`, since
+/// flattened clap structs share one namespace. Arguments which are not config values, such as the
+/// other fields of the struct, take the raw values given for them on the command line in `matches`.
+/// Flags which take no value are only passed when they change the default, list values are repeated
+/// or joined with the argument's delimiter, and positional values come last. Arguments without a
+/// value are left out.
+pub fn where_args(command: &Command, config: &Value, matches: &ArgMatches) -> Vec<String> {
+	let mut values = Map::new();
+	collect_values(config, &mut values);
+
+	let mut flags = Vec::new();
+	let mut positionals = Vec::new();
+	for arg in command.get_arguments() {
+		let Some(value) = values
+			.get(arg.get_id().as_str())
+			.cloned()
+			.or_else(|| matched_value(arg, matches))
+		else {
+			continue;
+		};
+		let value = &value;
+		if arg.is_positional() {
+			positionals.extend(items(value).into_iter().map(render));
+			continue;
+		}
+		let Some(flag) = flag(arg) else {
+			continue;
+		};
+
+		match arg.get_action() {
+			ArgAction::SetTrue if value == &Value::Bool(true) => flags.push(flag),
+			ArgAction::SetFalse if value == &Value::Bool(false) => flags.push(flag),
+			ArgAction::SetTrue | ArgAction::SetFalse => {}
+			ArgAction::Count => {
+				let count = value.as_u64().unwrap_or_default();
+				flags.extend((0..count).map(|_| flag.clone()));
+			}
+			_ => {
+				let items = items(value);
+				match arg.get_value_delimiter() {
+					Some(delimiter) if !items.is_empty() => {
+						let joined: Vec<String> = items.into_iter().map(render).collect();
+						flags.push(flag);
+						flags.push(joined.join(&delimiter.to_string()));
+					}
+					_ => {
+						for item in items {
+							flags.push(flag.clone());
+							flags.push(render(item));
+						}
+					}
+				}
+			}
+		}
+	}
+
+	flags.extend(positionals);
+	flags
+}
+
+/// Joins a command line, quoting arguments for a POSIX shell where needed.
+pub fn shell_join(args: &[String]) -> String {
+	args.iter()
+		.map(|arg| {
+			let plain = !arg.is_empty()
+				&& arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=,@%+".contains(c));
+			if plain {
+				arg.clone()
+			} else {
+				format!("'{}'", arg.replace('\'', "'\\''"))
+			}
+		})
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+fn collect_values(value: &Value, values: &mut Map<String, Value>) {
+	let Value::Object(map) = value else {
+		return;
+	};
+	for (key, value) in map {
+		if value.is_object() {
+			collect_values(value, values);
+		}
+		values.entry(key.clone()).or_insert_with(|| value.clone());
+	}
+}
+
+/// The value given for `arg` on the command line, in the shape of a config value.
+fn matched_value(arg: &Arg, matches: &ArgMatches) -> Option<Value> {
+	let id = arg.get_id().as_str();
+	if matches.value_source(id) != Some(ValueSource::CommandLine) {
+		return None;
+	}
+	match arg.get_action() {
+		ArgAction::SetTrue => Some(Value::Bool(true)),
+		ArgAction::SetFalse => Some(Value::Bool(false)),
+		ArgAction::Count => Some(Value::from(matches.get_count(id))),
+		_ => {
+			let raw = matches.try_get_raw(id).ok()??;
+			Some(Value::Array(
+				raw.map(|value| Value::String(value.to_string_lossy().into_owned())).collect(),
+			))
+		}
+	}
+}
+
+fn flag(arg: &Arg) -> Option<String> {
+	match (arg.get_long(), arg.get_short()) {
+		(Some(long), _) => Some(format!("--{}", long)),
+		(None, Some(short)) => Some(format!("-{}", short)),
+		(None, None) => None,
+	}
+}
+
+/// The values of an argument, skipping nulls left by unset options.
+fn items(value: &Value) -> Vec<&Value> {
+	match value {
+		Value::Array(items) => items.iter().filter(|item| !item.is_null()).collect(),
+		Value::Null => Vec::new(),
+		value => vec![value],
+	}
+}
+
+fn render(value: &Value) -> String {
+	match value {
+		Value::String(s) => s.clone(),
+		value => value.to_string(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn test_where_prefix() {
+		let args = ["/usr/bin/tool", "add", "using", "--args-path", "a.json"].map(String::from);
+		assert_eq!(where_prefix(args), vec!["tool", "add", "where"]);
+		assert_eq!(where_prefix(["tool".to_string()]), vec!["tool", "where"]);
+	}
+
+	#[test]
+	fn test_where_args() {
+		let command = Command::new("add")
+			.arg(Arg::new("left").long("left"))
+			.arg(Arg::new("peers").long("peers").action(ArgAction::Append))
+			.arg(Arg::new("tags").long("tags").value_delimiter(','))
+			.arg(Arg::new("verbose").long("verbose").action(ArgAction::SetTrue))
+			.arg(Arg::new("dry_run").long("dry-run").action(ArgAction::SetTrue))
+			.arg(Arg::new("ratio").long("ratio"))
+			.arg(Arg::new("host").long("host"))
+			.arg(Arg::new("name"));
+		let config = json!({
+			"args": {
+				"left": 3,
+				"peers": ["a", "b"],
+				"tags": ["x", "y"],
+				"verbose": true,
+				"dry_run": false,
+				"ratio": null,
+				"db": { "host": "local host" },
+				"name": "n",
+			}
+		});
+
+		let args = where_args(&command, &config, &ArgMatches::default());
+		assert_eq!(
+			shell_join(&args),
+			"--left 3 --peers a --peers b --tags x,y --verbose --host 'local host' n"
+		);
+	}
+
+	#[test]
+	fn test_where_args_from_matches() {
+		let command = Command::new("add")
+			.arg(Arg::new("left").long("left"))
+			.arg(Arg::new("label").long("label").action(ArgAction::Append))
+			.arg(Arg::new("quiet").short('q').action(ArgAction::Count))
+			.arg(Arg::new("color").long("color").default_value("auto"));
+		let args = ["using", "--label", "a", "--label", "b c", "-qq"];
+		let matches = command.clone().try_get_matches_from(args).unwrap();
+
+		let args = where_args(&command, &json!({ "args": { "left": 3 } }), &matches);
+		assert_eq!(shell_join(&args), "--left 3 --label a --label 'b c' -q -q");
+		assert_eq!(
+			resolving_args(&["tool", "add", "explain", "-q"].map(String::from)),
+			vec!["explain", "-q"]
+		);
+	}
+}
@@ -0,0 +1,375 @@
+use crate::format::Format;
+use crate::path::{KeyPath, Segment};
+use crate::{schema, template};
+use serde_json::{Map, Value};
+use std::marker::PhantomData;
+
+/// Describes how the fields of an `#[orfile(config)]` type are loaded by `using`.
+///
+/// Derive this with `#[derive(OrfileConfig)]` on a config type. Config types without it are still
+/// loaded, but do not take part in features which need their fields, such as strict checks and schemas.
+pub trait OrfileConfig {
+	/// The fields of the config type, in declaration order.
+	fn fields() -> Vec<Field>;
+
+	/// The doc comment of the config type, used to describe it in its JSON Schema.
+	fn description() -> Option<&'static str> {
+		None
+	}
+}
+
+/// Lets generated code use the [OrfileConfig] of a config type when it has one.
+///
+/// With [Derived] and [Underived] in scope, a method called on `&Probe::<T>::new()` comes from
+/// [Derived] when `T` implements [OrfileConfig], and otherwise from [Underived], which returns `None`.
+#[doc(hidden)]
+pub struct Probe<T>(PhantomData<T>);
+
+impl<T> Probe<T> {
+	#[allow(clippy::new_without_default)]
+	pub fn new() -> Self {
+		Self(PhantomData)
+	}
+}
+
+#[doc(hidden)]
+pub trait Derived {
+	fn fields(&self) -> Option<Vec<Field>>;
+	fn schema(&self, strict: bool) -> Option<Value>;
+	fn template(&self, format: Format) -> Option<String>;
+}
+
+impl<T: OrfileConfig> Derived for Probe<T> {
+	fn fields(&self) -> Option<Vec<Field>> {
+		Some(T::fields())
+	}
+
+	fn schema(&self, strict: bool) -> Option<Value> {
+		Some(schema::for_config::<T>(strict))
+	}
+
+	fn template(&self, format: Format) -> Option<String> {
+		Some(template::for_config::<T>(format))
+	}
+}
+
+#[doc(hidden)]
+pub trait Underived {
+	fn fields(&self) -> Option<Vec<Field>> {
+		None
+	}
+
+	fn schema(&self, _strict: bool) -> Option<Value> {
+		None
+	}
+
+	fn template(&self, _format: Format) -> Option<String> {
+		None
+	}
+}
+
+impl<T> Underived for &Probe<T> {}
+
+/// How values for an array field from a later layer combine with those from earlier layers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeStrategy {
+	/// The later layer's array replaces the earlier one.
+	#[default]
+	Replace,
+	/// The later layer's items are appended to the earlier ones.
+	Append,
+}
+
+/// The JSON shape of a field's type, as far as it can be told from the Rust type.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Kind {
+	/// Any value, for types the derive does not recognize.
+	#[default]
+	Any,
+	Boolean,
+	Integer {
+		unsigned: bool,
+	},
+	Number,
+	String,
+	/// An array of items of the inner kind, for `Vec<T>` and sets.
+	Array(Box<Kind>),
+	/// An object, described by the nested fields when the field is marked `#[orfile(nested)]`.
+	Object,
+	/// A value of the inner kind or null, for `Option<T>`.
+	Option(Box<Kind>),
+}
+
+/// A field of a config type.
+#[derive(Debug, Clone)]
+pub struct Field {
+	/// The key of the field in the merged config.
+	pub name: &'static str,
+	/// How arrays for this field combine across layers.
+	pub merge: MergeStrategy,
+	/// The fields of a nested config type, for fields marked `#[orfile(nested)]`.
+	pub nested: Vec<Field>,
+	/// A variable read instead of the prefixed one, set by `#[orfile(env = "...")]`.
+	pub env: Option<&'static str>,
+	/// Legacy keys accepted from files and args, set by `#[orfile(alias = "...")]`.
+	pub aliases: Vec<&'static str>,
+	/// Whether the field ignores the environment, set by `#[orfile(skip_env)]`.
+	pub skip_env: bool,
+	/// Whether the field holds a filesystem path, set by `#[orfile(path)]`.
+	pub path: bool,
+	/// The JSON shape of the field's type.
+	pub kind: Kind,
+	/// Whether the merged config must set the field, i.e. it is neither an `Option` nor has a serde default.
+	pub required: bool,
+	/// The doc comment of the field.
+	pub description: Option<&'static str>,
+	/// The default value from serde `default` or clap `default_value`, used by `init` templates.
+	pub default: Option<fn() -> Value>,
+}
+
+// Function pointers do not compare reliably, so fields are compared without their default
+impl PartialEq for Field {
+	fn eq(&self, other: &Self) -> bool {
+		self.name == other.name
+			&& self.merge == other.merge
+			&& self.nested == other.nested
+			&& self.env == other.env
+			&& self.aliases == other.aliases
+			&& self.skip_env == other.skip_env
+			&& self.path == other.path
+			&& self.kind == other.kind
+			&& self.required == other.required
+			&& self.description == other.description
+	}
+}
+
+impl Eq for Field {}
+
+impl Field {
+	pub fn new(name: &'static str) -> Self {
+		Self {
+			name,
+			merge: MergeStrategy::default(),
+			nested: Vec::new(),
+			env: None,
+			aliases: Vec::new(),
+			skip_env: false,
+			path: false,
+			kind: Kind::default(),
+			required: false,
+			description: None,
+			default: None,
+		}
+	}
+
+	pub fn with_merge(mut self, merge: MergeStrategy) -> Self {
+		self.merge = merge;
+		self
+	}
+
+	pub fn with_nested(mut self, nested: Vec<Field>) -> Self {
+		self.nested = nested;
+		self
+	}
+
+	pub fn with_env(mut self, env: &'static str) -> Self {
+		self.env = Some(env);
+		self
+	}
+
+	pub fn with_alias(mut self, alias: &'static str) -> Self {
+		self.aliases.push(alias);
+		self
+	}
+
+	pub fn skip_env(mut self) -> Self {
+		self.skip_env = true;
+		self
+	}
+
+	pub fn path(mut self) -> Self {
+		self.path = true;
+		self
+	}
+
+	pub fn with_kind(mut self, kind: Kind) -> Self {
+		self.kind = kind;
+		self
+	}
+
+	pub fn required(mut self) -> Self {
+		self.required = true;
+		self
+	}
+
+	pub fn with_description(mut self, description: &'static str) -> Self {
+		self.description = Some(description);
+		self
+	}
+
+	pub fn with_default(mut self, default: fn() -> Value) -> Self {
+		self.default = Some(default);
+		self
+	}
+}
+
+/// Finds the field with the given key.
+pub fn find<'a>(fields: &'a [Field], key: &str) -> Option<&'a Field> {
+	fields.iter().find(|field| field.name == key)
+}
+
+/// Finds the field whose name or one of whose aliases is `key`.
+pub fn find_alias<'a>(fields: &'a [Field], key: &str) -> Option<&'a Field> {
+	fields.iter().find(|field| field.name == key || field.aliases.contains(&key))
+}
+
+/// Lists the fields along `path`, stopping at the first key which is not a known field.
+///
+/// Array indices stay within the fields of the array field, since its items share them.
+pub fn fields_along<'a>(fields: &'a [Field], path: &KeyPath) -> Vec<&'a Field> {
+	let mut chain = Vec::new();
+	let mut fields = fields;
+	for segment in &path.0 {
+		let Segment::Key(key) = segment else {
+			continue;
+		};
+		let Some(field) = find(fields, key) else {
+			break;
+		};
+		chain.push(field);
+		fields = &field.nested;
+	}
+	chain
+}
+
+/// Rewrites aliases in `path` to the names of their fields.
+pub fn canonical_path(fields: &[Field], path: &KeyPath) -> KeyPath {
+	let mut fields = fields;
+	let mut segments = Vec::with_capacity(path.0.len());
+	for segment in &path.0 {
+		match segment {
+			Segment::Key(key) => match find_alias(fields, key) {
+				Some(field) => {
+					segments.push(Segment::Key(field.name.to_string()));
+					fields = &field.nested;
+				}
+				None => {
+					segments.push(segment.clone());
+					fields = &[];
+				}
+			},
+			Segment::Index(_) => segments.push(segment.clone()),
+		}
+	}
+	KeyPath(segments)
+}
+
+/// Rewrites aliased keys in `map` to the names of their fields, through nested fields.
+///
+/// When a map has both an alias and the field name, the value under the field name wins.
+pub fn canonicalize(fields: &[Field], map: Map<String, Value>) -> Map<String, Value> {
+	let mut canonical = Map::new();
+	for (key, value) in map {
+		let Some(field) = find_alias(fields, &key) else {
+			canonical.insert(key, value);
+			continue;
+		};
+
+		let value = match value {
+			Value::Object(object) => Value::Object(canonicalize(&field.nested, object)),
+			Value::Array(items) => Value::Array(
+				items
+					.into_iter()
+					.map(|item| match item {
+						Value::Object(object) => Value::Object(canonicalize(&field.nested, object)),
+						item => item,
+					})
+					.collect(),
+			),
+			value => value,
+		};
+		if key == field.name || !canonical.contains_key(field.name) {
+			canonical.insert(field.name.to_string(), value);
+		}
+	}
+	canonical
+}
+
+/// Lists the variables bound with `#[orfile(env = "...")]` and the paths they set.
+pub fn bound_env(fields: &[Field]) -> Vec<(KeyPath, &'static str)> {
+	fn collect(fields: &[Field], prefix: &KeyPath, bound: &mut Vec<(KeyPath, &'static str)>) {
+		for field in fields {
+			let mut path = prefix.clone();
+			path.0.push(Segment::Key(field.name.to_string()));
+			if let Some(env) = field.env {
+				bound.push((path.clone(), env));
+			}
+			collect(&field.nested, &path, bound);
+		}
+	}
+
+	let mut bound = Vec::new();
+	collect(fields, &KeyPath::default(), &mut bound);
+	bound
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn test_canonicalize_aliases() {
+		let fields = vec![
+			Field::new("left").with_alias("lhs"),
+			Field::new("db")
+				.with_alias("database")
+				.with_nested(vec![Field::new("host").with_alias("hostname")]),
+		];
+		let map =
+			json!({ "lhs": 1, "database": { "hostname": "a" }, "db": { "host": "b" }, "other": 2 });
+
+		let canonical = canonicalize(&fields, map.as_object().unwrap().clone());
+		assert_eq!(
+			Value::Object(canonical),
+			json!({ "left": 1, "db": { "host": "b" }, "other": 2 })
+		);
+		assert_eq!(
+			canonical_path(&fields, &KeyPath::parse_dotted("database.hostname")).to_string(),
+			"db.host"
+		);
+	}
+}
+
+/// A config type shared by the tests of modules which describe config types, such as schemas and templates.
+#[cfg(test)]
+pub(crate) mod fixture {
+	use super::*;
+	use serde_json::json;
+
+	pub(crate) struct Args;
+
+	impl OrfileConfig for Args {
+		fn fields() -> Vec<Field> {
+			vec![
+				Field::new("left")
+					.with_kind(Kind::Integer { unsigned: true })
+					.required()
+					.with_description("The left number"),
+				Field::new("name").with_kind(Kind::String).with_default(|| json!("add")),
+				Field::new("ratio").with_kind(Kind::Option(Box::new(Kind::Number))),
+				Field::new("peers").with_kind(Kind::Array(Box::new(Kind::String))),
+				Field::new("db").with_kind(Kind::Object).required().with_nested(vec![Field::new(
+					"port",
+				)
+				.with_kind(Kind::Integer { unsigned: true })
+				.with_alias("p")
+				.required()]),
+			]
+		}
+
+		fn description() -> Option<&'static str> {
+			Some("The arguments")
+		}
+	}
+}
@@ -0,0 +1,92 @@
+use crate::format::Format;
+use std::path::{Path, PathBuf};
+
+/// Lists the config files found for a `#[orfile(discover = "...")]` name, in merge order.
+///
+/// See [candidates] for the locations searched. At each location the first file with a known
+/// extension is used, trying `.json`, `.toml`, `.yaml` and `.yml` in turn.
+pub fn discover(name: &str) -> Vec<PathBuf> {
+	let cwd = std::env::current_dir().unwrap_or_default();
+	candidates(name, &cwd, |key| std::env::var(key).ok())
+		.into_iter()
+		.filter_map(|base| {
+			Format::ALL
+				.iter()
+				.flat_map(|format| format.extensions())
+				.map(|ext| with_extension(&base, ext))
+				.find(|path| path.is_file())
+		})
+		.collect()
+}
+
+/// Lists the paths, without extension, searched for the config named `name`, such as `tool/add`.
+///
+/// The locations are listed from the lowest precedence to the highest, so files found later are
+/// merged over earlier ones:
+/// 1. `/etc/tool/add`
+/// 2. `$XDG_CONFIG_HOME/tool/add`, or `$HOME/.config/tool/add` when it is not set
+/// 3. `.tool/add` in each parent directory of `cwd`, from the root down, and then in `cwd` itself
+pub fn candidates(name: &str, cwd: &Path, var: impl Fn(&str) -> Option<String>) -> Vec<PathBuf> {
+	let mut candidates = vec![Path::new("/etc").join(name)];
+
+	let config_home = var("XDG_CONFIG_HOME")
+		.map(PathBuf::from)
+		.filter(|path| path.is_absolute())
+		.or_else(|| var("HOME").map(|home| Path::new(&home).join(".config")));
+	if let Some(config_home) = config_home {
+		candidates.push(config_home.join(name));
+	}
+
+	let mut ancestors: Vec<&Path> = cwd.ancestors().collect();
+	ancestors.reverse();
+	candidates.extend(ancestors.into_iter().map(|dir| dir.join(format!(".{}", name))));
+
+	let mut unique = Vec::with_capacity(candidates.len());
+	for candidate in candidates {
+		if !unique.contains(&candidate) {
+			unique.push(candidate);
+		}
+	}
+	unique
+}
+
+/// Appends an extension, keeping any dots already in the file name such as in `add.args`.
+fn with_extension(base: &Path, extension: &str) -> PathBuf {
+	let mut path = base.as_os_str().to_owned();
+	path.push(".");
+	path.push(extension);
+	PathBuf::from(path)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_candidates() {
+		let var = |key: &str| match key {
+			"HOME" => Some("/home/me".to_string()),
+			_ => None,
+		};
+
+		assert_eq!(
+			candidates("tool/add", Path::new("/work/repo"), var),
+			[
+				"/etc/tool/add",
+				"/home/me/.config/tool/add",
+				"/.tool/add",
+				"/work/.tool/add",
+				"/work/repo/.tool/add",
+			]
+			.map(PathBuf::from)
+		);
+	}
+
+	#[test]
+	fn test_with_extension() {
+		assert_eq!(
+			with_extension(Path::new("/etc/tool/add.args"), "toml"),
+			PathBuf::from("/etc/tool/add.args.toml")
+		);
+	}
+}
@@ -0,0 +1,141 @@
+use crate::config::{self, Field};
+use crate::path::KeyPath;
+use serde_json::{Map, Value};
+
+/// The separator between nested keys in environment variable names.
+pub const NESTED_SEPARATOR: &str = "__";
+
+/// An environment variable which sets a config value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variable {
+	/// The name of the variable, such as `ADD_DB__PORT`.
+	pub name: String,
+	/// The config path the variable sets, such as `db.port`.
+	pub path: KeyPath,
+	/// The raw value of the variable.
+	pub value: String,
+}
+
+/// Collects the process environment variables starting with `prefix` into a config map.
+pub fn layer(prefix: &str) -> Map<String, Value> {
+	layer_from(prefix, std::env::vars())
+}
+
+/// Collects the variables starting with `prefix` into a config map.
+///
+/// Values are kept as strings to be coerced to the field types on deserialization.
+pub fn layer_from<I>(prefix: &str, vars: I) -> Map<String, Value>
+where
+	I: IntoIterator<Item = (String, String)>,
+{
+	let mut map = Map::new();
+	for variable in variables_from(prefix, vars) {
+		variable.path.insert(&mut map, Value::String(variable.value));
+	}
+	map
+}
+
+/// Finds the variables starting with `prefix` and the config paths they set.
+///
+/// The remainder of each name is lowercased and split on [NESTED_SEPARATOR] into a nested path,
+/// so with the prefix `ADD_` both `ADD_DB__PORT` and `ADD__DB__PORT` set `db.port`.
+pub fn variables_from<I>(prefix: &str, vars: I) -> Vec<Variable>
+where
+	I: IntoIterator<Item = (String, String)>,
+{
+	// Sort so that conflicting variables such as `ADD_DB` and `ADD_DB__PORT` resolve the same way on every run
+	let mut vars: Vec<_> = vars.into_iter().collect();
+	vars.sort();
+
+	vars.into_iter()
+		.filter_map(|(name, value)| {
+			let suffix = name.strip_prefix(prefix)?;
+			let path = KeyPath::from_keys(
+				suffix
+					.split(NESTED_SEPARATOR)
+					.map(|segment| segment.trim_matches('_').to_ascii_lowercase())
+					.filter(|segment| !segment.is_empty()),
+			);
+			(!path.is_empty()).then_some(Variable { name, path, value })
+		})
+		.collect()
+}
+
+/// Finds the variables which set fields of a config type.
+///
+/// These are the variables starting with `prefix`, as found by [variables_from], with aliases
+/// rewritten to field names. Fields marked `#[orfile(skip_env)]` are left out, and fields bound
+/// with `#[orfile(env = "...")]` read their bound variable instead of the prefixed one.
+pub fn variables_for<I>(prefix: &str, fields: &[Field], vars: I) -> Vec<Variable>
+where
+	I: IntoIterator<Item = (String, String)>,
+{
+	let vars: Vec<_> = vars.into_iter().collect();
+
+	let mut variables: Vec<_> = variables_from(prefix, vars.iter().cloned())
+		.into_iter()
+		.map(|variable| Variable {
+			path: config::canonical_path(fields, &variable.path),
+			..variable
+		})
+		.filter(|variable| {
+			let chain = config::fields_along(fields, &variable.path);
+			!chain.iter().any(|field| field.skip_env || field.env.is_some())
+		})
+		.collect();
+
+	for (path, name) in config::bound_env(fields) {
+		if let Some((_, value)) = vars.iter().find(|(key, _)| key == name) {
+			variables.push(Variable { name: name.to_string(), path, value: value.clone() });
+		}
+	}
+	variables
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn test_env_layer_nests_on_double_underscore() {
+		let vars = [
+			("ADD_LEFT", "1"),
+			("ADD_LEFT_PAD", "2"),
+			("ADD_DB__HOST", "localhost"),
+			("ADD__DB__PORT", "5432"),
+			("OTHER_LEFT", "3"),
+		]
+		.map(|(k, v)| (k.to_string(), v.to_string()));
+
+		assert_eq!(
+			Value::Object(layer_from("ADD_", vars)),
+			json!({
+				"left": "1",
+				"left_pad": "2",
+				"db": { "host": "localhost", "port": "5432" },
+			})
+		);
+	}
+
+	#[test]
+	fn test_env_field_options() {
+		let fields = vec![
+			Field::new("url").with_env("DATABASE_URL"),
+			Field::new("secret").skip_env(),
+			Field::new("left").with_alias("lhs"),
+		];
+		let vars = [
+			("ADD_URL", "ignored"),
+			("DATABASE_URL", "postgres://db"),
+			("ADD_SECRET", "ignored"),
+			("ADD_LHS", "1"),
+		]
+		.map(|(k, v)| (k.to_string(), v.to_string()));
+
+		let variables = variables_for("ADD_", &fields, vars);
+		let names: Vec<_> =
+			variables.iter().map(|v| (v.name.as_str(), v.path.to_string())).collect();
+		assert_eq!(names, [("ADD_LHS", "left".to_string()), ("DATABASE_URL", "url".to_string())]);
+	}
+}
@@ -0,0 +1,41 @@
+use crate::format::Format;
+
+/// Errors raised while loading parameters for a `using` resolution.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("failed to read config file at {path}")]
+	Io { path: String, source: std::io::Error },
+
+	#[error("failed to write config file at {path}")]
+	Write { path: String, source: std::io::Error },
+
+	#[error("unknown config file format for {path}: expected one of {expected}")]
+	UnknownFormat { path: String, expected: String },
+
+	#[error("{format} config files require the `{feature}` feature of orfile")]
+	FormatDisabled { format: Format, feature: &'static str },
+
+	#[error("failed to parse {format} config: {message}")]
+	Parse { format: Format, message: String },
+
+	#[error("failed to serialize {format} config: {message}")]
+	Serialize { format: Format, message: String },
+
+	#[error("invalid extra arg `{arg}` at position {position}: {reason}")]
+	InvalidArg { position: usize, arg: String, reason: String },
+
+	#[error("unknown key `{path}` from {origin}{}", suggestion.as_ref().map(|s| format!("; did you mean `{}`?", s)).unwrap_or_default())]
+	UnknownKey { path: String, origin: String, suggestion: Option<String> },
+
+	#[error("config file {path} does not match its schema at `{key}`{}: {reason}", line.map(|line| format!(" (line {})", line)).unwrap_or_default())]
+	Schema { path: String, key: String, line: Option<usize>, reason: String },
+
+	#[error("profile `{profile}` is not defined in any config file{}", if available.is_empty() { String::new() } else { format!(" (available: {})", available.join(", ")) })]
+	UnknownProfile { profile: String, available: Vec<String> },
+
+	#[error("failed to interpolate `{key}` in config file {path}: {reason}")]
+	Interpolation { path: String, key: String, reason: String },
+
+	#[error("invalid `$extends` in config file {path}: {reason}")]
+	Extends { path: String, reason: String },
+}
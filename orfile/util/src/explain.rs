@@ -0,0 +1,147 @@
+use crate::path::KeyPath;
+use crate::provenance::{Origin, Provenance};
+use serde_json::{json, Map, Value};
+
+/// A resolved config value with its origin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+	pub path: KeyPath,
+	pub value: Value,
+	pub origin: Origin,
+}
+
+/// The resolved config values of a `using` invocation, annotated with the layer each came from.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Explanation {
+	pub entries: Vec<Entry>,
+}
+
+impl Explanation {
+	/// Pairs every path in `provenance` with its value in the resolved config.
+	///
+	/// Paths which the resolved config no longer holds, such as keys skipped on deserialization, are
+	/// left out.
+	pub fn new(resolved: &Map<String, Value>, provenance: &Provenance) -> Self {
+		let entries = provenance
+			.iter()
+			.filter_map(|(path, origin)| {
+				let value = path.get(resolved)?.clone();
+				Some(Entry { path: path.clone(), value, origin: origin.clone() })
+			})
+			.collect();
+		Self { entries }
+	}
+
+	/// Nests every path under `key`, as when a config is one field of a larger struct.
+	pub fn prefixed(self, key: &str) -> Self {
+		Self {
+			entries: self
+				.entries
+				.into_iter()
+				.map(|entry| Entry { path: entry.path.prefixed(key), ..entry })
+				.collect(),
+		}
+	}
+
+	pub fn extend(&mut self, other: Explanation) {
+		self.entries.extend(other.entries);
+	}
+
+	/// Renders the entries as an aligned table of path, value and source.
+	pub fn to_table(&self) -> String {
+		let rows: Vec<[String; 3]> = self
+			.entries
+			.iter()
+			.map(|entry| {
+				let mut source = entry.origin.source.to_string();
+				if !entry.origin.overridden.is_empty() {
+					let overridden: Vec<_> =
+						entry.origin.overridden.iter().map(ToString::to_string).collect();
+					source.push_str(&format!(" (over {})", overridden.join(", ")));
+				}
+				[entry.path.to_string(), display_value(&entry.value), source]
+			})
+			.collect();
+
+		let header = ["PATH".to_string(), "VALUE".to_string(), "SOURCE".to_string()];
+		let widths: Vec<usize> = (0..2)
+			.map(|column| {
+				rows.iter().chain([&header]).map(|row| row[column].len()).max().unwrap_or(0)
+			})
+			.collect();
+
+		let mut table = String::new();
+		for row in [&header].into_iter().chain(rows.iter()) {
+			table.push_str(&format!(
+				"{:path$}  {:value$}  {}\n",
+				row[0],
+				row[1],
+				row[2],
+				path = widths[0],
+				value = widths[1]
+			));
+		}
+		table
+	}
+
+	/// Renders the entries as a JSON array of `path`, `value`, `source` and `overridden`.
+	pub fn to_json(&self) -> Value {
+		Value::Array(
+			self.entries
+				.iter()
+				.map(|entry| {
+					json!({
+						"path": entry.path.to_string(),
+						"value": entry.value,
+						"source": entry.origin.source.to_string(),
+						"overridden": entry
+							.origin
+							.overridden
+							.iter()
+							.map(ToString::to_string)
+							.collect::<Vec<_>>(),
+					})
+				})
+				.collect(),
+		)
+	}
+}
+
+/// Shows strings without quotes, so they read the same as numbers and booleans.
+fn display_value(value: &Value) -> String {
+	match value {
+		Value::String(s) => s.clone(),
+		value => value.to_string(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::provenance::Source;
+
+	#[test]
+	fn test_explanation_table() {
+		let mut provenance = Provenance::default();
+		provenance
+			.record(KeyPath::parse_dotted("left"), Source::Env { name: "ADD_LEFT".to_string() });
+		provenance.record(
+			KeyPath::parse_dotted("left"),
+			Source::File { path: "config.json".to_string(), line: Some(2) },
+		);
+		provenance.record(
+			KeyPath::parse_dotted("right"),
+			Source::Arg { flag: "--right".to_string(), positions: vec![1] },
+		);
+		let merged = json!({ "left": 3, "right": "4" }).as_object().unwrap().clone();
+
+		let explanation = Explanation::new(&merged, &provenance).prefixed("args");
+		assert_eq!(
+			explanation.to_table(),
+			"PATH        VALUE  SOURCE\n\
+			 args.left   3      file config.json:2 (over env ADD_LEFT)\n\
+			 args.right  4      arg --right at position 1\n"
+		);
+		assert_eq!(explanation.to_json()[1]["value"], json!("4"));
+	}
+}
@@ -0,0 +1,122 @@
+use crate::format::{read_document_as, Document, Format};
+use crate::Error;
+use serde_json::Value;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+
+/// The top level key listing the config files a config file extends.
+pub const EXTENDS: &str = "$extends";
+
+/// Reads a config file along with the files it extends, in the order they should be merged.
+///
+/// A top level `$extends` key holds a path or an array of paths, relative to the directory of the
+/// file declaring them. Each extended file is read the same way and comes before the file
+/// extending it, so the file's own keys take precedence, and later entries of `$extends` take
+/// precedence over earlier ones. The `$extends` key is removed from the returned documents. A file
+/// which extends itself, directly or through other files, is an error.
+pub async fn read_documents(path: impl AsRef<Path>) -> Result<Vec<Document>, Error> {
+	read_documents_as(path, None).await
+}
+
+/// Like [read_documents], reading the file in `format` when given, as [read_document_as] does.
+///
+/// The extended files are always read in the format matching their extension. Those extended by a
+/// document read from standard input are relative to the current directory.
+pub async fn read_documents_as(
+	path: impl AsRef<Path>,
+	format: Option<Format>,
+) -> Result<Vec<Document>, Error> {
+	let mut documents = Vec::new();
+	read(path.as_ref().to_path_buf(), format, &mut Vec::new(), &mut documents).await?;
+	Ok(documents)
+}
+
+/// Reads one file of the chain, with `stack` holding the files extending it.
+fn read<'a>(
+	path: PathBuf,
+	format: Option<Format>,
+	stack: &'a mut Vec<(PathBuf, String)>,
+	documents: &'a mut Vec<Document>,
+) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send + 'a>> {
+	Box::pin(async move {
+		let mut document = read_document_as(&path, format).await?;
+		let canonical = tokio::fs::canonicalize(&path).await.unwrap_or_else(|_| path.clone());
+		if stack.iter().any(|(seen, _)| seen == &canonical) {
+			let mut cycle: Vec<&str> = stack.iter().map(|(_, display)| display.as_str()).collect();
+			cycle.push(&document.path);
+			return Err(Error::Extends {
+				path: document.path.clone(),
+				reason: format!("circular `{}` through {}", EXTENDS, cycle.join(" -> ")),
+			});
+		}
+
+		let bases = bases(&mut document)?;
+		let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+		stack.push((canonical, document.path.clone()));
+		for base in bases {
+			read(dir.join(base), None, stack, documents).await?;
+		}
+		stack.pop();
+
+		documents.push(document);
+		Ok(())
+	})
+}
+
+/// Removes the `$extends` key of a document, returning the paths it lists.
+fn bases(document: &mut Document) -> Result<Vec<String>, Error> {
+	let Value::Object(map) = &mut document.value else {
+		return Ok(Vec::new());
+	};
+	let invalid = |document: &Document| Error::Extends {
+		path: document.path.clone(),
+		reason: format!("`{}` must be a path or an array of paths", EXTENDS),
+	};
+	match map.remove(EXTENDS) {
+		None => Ok(Vec::new()),
+		Some(Value::String(base)) => Ok(vec![base]),
+		Some(Value::Array(items)) => items
+			.into_iter()
+			.map(|item| match item {
+				Value::String(base) => Ok(base),
+				_ => Err(invalid(document)),
+			})
+			.collect(),
+		Some(_) => Err(invalid(document)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	fn write(dir: &Path, name: &str, value: Value) {
+		let path = dir.join(name);
+		std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+		std::fs::write(path, value.to_string()).unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_read_documents() {
+		let dir = std::env::temp_dir().join(format!("orfile-extends-{}", std::process::id()));
+		write(&dir, "base.json", json!({ "left": 1, "right": 1 }));
+		write(&dir, "shared/right.json", json!({ "$extends": "../base.json", "right": 2 }));
+		write(&dir, "tool/add.json", json!({ "$extends": ["../shared/right.json"], "left": 3 }));
+		write(&dir, "a.json", json!({ "$extends": "b.json" }));
+		write(&dir, "b.json", json!({ "$extends": ["a.json"] }));
+
+		let documents = read_documents(dir.join("tool/add.json")).await.unwrap();
+		let values: Vec<Value> = documents.into_iter().map(|document| document.value).collect();
+		assert_eq!(
+			values,
+			vec![json!({ "left": 1, "right": 1 }), json!({ "right": 2 }), json!({ "left": 3 })]
+		);
+
+		let err = read_documents(dir.join("a.json")).await.unwrap_err();
+		assert!(err.to_string().contains("circular `$extends`"), "{}", err);
+
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+}
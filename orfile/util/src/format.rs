@@ -0,0 +1,380 @@
+use crate::path::KeyPath;
+use crate::span;
+use crate::Error;
+use serde_json::Value;
+use std::fmt;
+use std::path::Path;
+use tokio::io::AsyncReadExt;
+use tokio::sync::OnceCell;
+
+/// The config file path which reads the document from standard input.
+pub const STDIN: &str = "-";
+
+/// The name standard input goes by in errors and provenance.
+const STDIN_NAME: &str = "<stdin>";
+
+/// The contents of standard input, which can only be read once, kept for every later use of `-`.
+static STDIN_CONTENTS: OnceCell<String> = OnceCell::const_new();
+
+/// A config file format understood by `using`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+	Json,
+	Toml,
+	Yaml,
+}
+
+impl Format {
+	/// All formats, in the order they are listed in error messages.
+	pub const ALL: &'static [Format] = &[Format::Json, Format::Toml, Format::Yaml];
+
+	/// The file extensions recognized for this format.
+	pub fn extensions(&self) -> &'static [&'static str] {
+		match self {
+			Format::Json => &["json"],
+			Format::Toml => &["toml"],
+			Format::Yaml => &["yaml", "yml"],
+		}
+	}
+
+	/// Finds the format matching a file extension, ignoring case.
+	pub fn from_extension(extension: &str) -> Option<Self> {
+		Self::ALL.iter().copied().find(|format| {
+			format.extensions().iter().any(|ext| ext.eq_ignore_ascii_case(extension))
+		})
+	}
+
+	/// Detects the format of a config file from its extension.
+	pub fn from_path(path: &Path) -> Result<Self, Error> {
+		path.extension()
+			.and_then(|ext| ext.to_str())
+			.and_then(Self::from_extension)
+			.ok_or_else(|| Error::UnknownFormat {
+				path: path.display().to_string(),
+				expected: Self::ALL
+					.iter()
+					.flat_map(|format| format.extensions())
+					.map(|ext| format!(".{}", ext))
+					.collect::<Vec<_>>()
+					.join(", "),
+			})
+	}
+
+	/// Guesses the format of a config document which has no file extension, such as one piped in.
+	///
+	/// JSON is tried first, then TOML, and YAML is assumed for anything else since it accepts most
+	/// documents. Without the `yaml` feature, JSON is assumed instead so its parse error is reported.
+	pub fn sniff(contents: &str) -> Self {
+		if serde_json::from_str::<Value>(contents).is_ok() {
+			Format::Json
+		} else if Format::Toml.parse(contents).is_ok() {
+			Format::Toml
+		} else if cfg!(feature = "yaml") {
+			Format::Yaml
+		} else {
+			Format::Json
+		}
+	}
+
+	/// Parses a config document into a JSON value.
+	pub fn parse(&self, contents: &str) -> Result<Value, Error> {
+		match self {
+			Format::Json => serde_json::from_str(contents)
+				.map_err(|e| Error::Parse { format: *self, message: e.to_string() }),
+			Format::Toml => parse_toml(contents),
+			Format::Yaml => parse_yaml(contents),
+		}
+	}
+
+	/// Renders a JSON value as a config document which [Format::parse] reads back.
+	pub fn render(&self, value: &Value) -> Result<String, Error> {
+		match self {
+			Format::Json => serde_json::to_string_pretty(value)
+				.map(|json| format!("{}\n", json))
+				.map_err(|e| Error::Serialize { format: *self, message: e.to_string() }),
+			Format::Toml => render_toml(value),
+			Format::Yaml => render_yaml(value),
+		}
+	}
+}
+
+impl fmt::Display for Format {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Format::Json => write!(f, "JSON"),
+			Format::Toml => write!(f, "TOML"),
+			Format::Yaml => write!(f, "YAML"),
+		}
+	}
+}
+
+#[cfg(feature = "toml")]
+fn parse_toml(contents: &str) -> Result<Value, Error> {
+	let value: toml::Value = toml::from_str(contents)
+		.map_err(|e| Error::Parse { format: Format::Toml, message: e.to_string() })?;
+	Ok(toml_to_json(value))
+}
+
+#[cfg(not(feature = "toml"))]
+fn parse_toml(_contents: &str) -> Result<Value, Error> {
+	Err(Error::FormatDisabled { format: Format::Toml, feature: "toml" })
+}
+
+/// Renders TOML, leaving out null object values since TOML has no null.
+#[cfg(feature = "toml")]
+fn render_toml(value: &Value) -> Result<String, Error> {
+	fn strip_nulls(value: &Value) -> Value {
+		match value {
+			Value::Object(map) => Value::Object(
+				map.iter()
+					.filter(|(_, value)| !value.is_null())
+					.map(|(key, value)| (key.clone(), strip_nulls(value)))
+					.collect(),
+			),
+			Value::Array(items) => Value::Array(items.iter().map(strip_nulls).collect()),
+			value => value.clone(),
+		}
+	}
+
+	toml::to_string_pretty(&strip_nulls(value))
+		.map_err(|e| Error::Serialize { format: Format::Toml, message: e.to_string() })
+}
+
+#[cfg(not(feature = "toml"))]
+fn render_toml(_value: &Value) -> Result<String, Error> {
+	Err(Error::FormatDisabled { format: Format::Toml, feature: "toml" })
+}
+
+/// Converts a TOML value into JSON, rendering datetimes as strings.
+#[cfg(feature = "toml")]
+fn toml_to_json(value: toml::Value) -> Value {
+	match value {
+		toml::Value::String(s) => Value::String(s),
+		toml::Value::Integer(i) => Value::from(i),
+		toml::Value::Float(f) => {
+			serde_json::Number::from_f64(f).map(Value::Number).unwrap_or(Value::Null)
+		}
+		toml::Value::Boolean(b) => Value::Bool(b),
+		toml::Value::Datetime(dt) => Value::String(dt.to_string()),
+		toml::Value::Array(array) => Value::Array(array.into_iter().map(toml_to_json).collect()),
+		toml::Value::Table(table) => {
+			Value::Object(table.into_iter().map(|(k, v)| (k, toml_to_json(v))).collect())
+		}
+	}
+}
+
+/// Parses a single YAML document, resolving anchors and `<<` merge keys.
+///
+/// Multi-document streams are rejected because there is no single config to merge.
+#[cfg(feature = "yaml")]
+fn parse_yaml(contents: &str) -> Result<Value, Error> {
+	use serde::Deserialize;
+
+	let error = |message: String| Error::Parse { format: Format::Yaml, message };
+
+	let mut documents = serde_yaml::Deserializer::from_str(contents);
+	let mut value = match documents.next() {
+		Some(document) => {
+			serde_yaml::Value::deserialize(document).map_err(|e| error(e.to_string()))?
+		}
+		None => serde_yaml::Value::Null,
+	};
+	if documents.next().is_some() {
+		return Err(error(
+			"multi-document YAML is not supported; split the documents into separate files"
+				.to_string(),
+		));
+	}
+
+	value.apply_merge().map_err(|e| error(e.to_string()))?;
+	serde_json::to_value(value).map_err(|e| error(e.to_string()))
+}
+
+#[cfg(not(feature = "yaml"))]
+fn parse_yaml(_contents: &str) -> Result<Value, Error> {
+	Err(Error::FormatDisabled { format: Format::Yaml, feature: "yaml" })
+}
+
+#[cfg(feature = "yaml")]
+fn render_yaml(value: &Value) -> Result<String, Error> {
+	serde_yaml::to_string(value)
+		.map_err(|e| Error::Serialize { format: Format::Yaml, message: e.to_string() })
+}
+
+#[cfg(not(feature = "yaml"))]
+fn render_yaml(_value: &Value) -> Result<String, Error> {
+	Err(Error::FormatDisabled { format: Format::Yaml, feature: "yaml" })
+}
+
+/// A parsed config file, kept with its source text.
+#[derive(Debug, Clone)]
+pub struct Document {
+	/// The path the document was read from.
+	pub path: String,
+	pub format: Format,
+	pub contents: String,
+	pub value: Value,
+}
+
+impl Document {
+	/// Finds the 1-based line on which the value at `path` is set, as [span::line_of] does.
+	pub fn line_of(&self, path: &KeyPath) -> Option<usize> {
+		span::line_of(self.format, &self.contents, path)
+	}
+}
+
+/// Reads a config file and parses it according to its extension.
+pub async fn read_file(path: impl AsRef<Path>) -> Result<Value, Error> {
+	Ok(read_document(path).await?.value)
+}
+
+/// Reads a config file into a [Document], parsing it according to its extension.
+pub async fn read_document(path: impl AsRef<Path>) -> Result<Document, Error> {
+	read_document_as(path, None).await
+}
+
+/// Reads a config file into a [Document] in `format`, or the format matching its extension.
+///
+/// The path `-` reads the document from standard input instead, sniffing its format with
+/// [Format::sniff] when none is given. Standard input can only be read once per process.
+pub async fn read_document_as(
+	path: impl AsRef<Path>,
+	format: Option<Format>,
+) -> Result<Document, Error> {
+	let path = path.as_ref();
+	if path == Path::new(STDIN) {
+		return read_stdin(format).await;
+	}
+
+	let format = match format {
+		Some(format) => format,
+		None => Format::from_path(path)?,
+	};
+	let contents = tokio::fs::read_to_string(path)
+		.await
+		.map_err(|source| Error::Io { path: path.display().to_string(), source })?;
+	let value = format.parse(&contents)?;
+	Ok(Document { path: path.display().to_string(), format, contents, value })
+}
+
+async fn read_stdin(format: Option<Format>) -> Result<Document, Error> {
+	let contents = STDIN_CONTENTS
+		.get_or_try_init(|| async {
+			let mut contents = String::new();
+			tokio::io::stdin()
+				.read_to_string(&mut contents)
+				.await
+				.map_err(|source| Error::Io { path: STDIN_NAME.to_string(), source })?;
+			Ok::<_, Error>(contents)
+		})
+		.await?
+		.clone();
+	let format = format.unwrap_or_else(|| Format::sniff(&contents));
+	let value = format.parse(&contents)?;
+	Ok(Document { path: STDIN_NAME.to_string(), format, contents, value })
+}
+
+/// Writes a value as a config file in the format matching the file's extension.
+pub async fn write_file(path: impl AsRef<Path>, value: &Value) -> Result<(), Error> {
+	let path = path.as_ref();
+	let contents = Format::from_path(path)?.render(value)?;
+	tokio::fs::write(path, contents)
+		.await
+		.map_err(|source| Error::Write { path: path.display().to_string(), source })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_format_from_path() {
+		assert_eq!(Format::from_path(Path::new("config.json")).unwrap(), Format::Json);
+		assert_eq!(Format::from_path(Path::new("dir/Config.TOML")).unwrap(), Format::Toml);
+		assert_eq!(Format::from_path(Path::new("config.yml")).unwrap(), Format::Yaml);
+
+		let err = Format::from_path(Path::new("config.ini")).unwrap_err();
+		assert_eq!(
+			err.to_string(),
+			"unknown config file format for config.ini: expected one of .json, .toml, .yaml, .yml"
+		);
+	}
+
+	#[cfg(feature = "toml")]
+	#[test]
+	fn test_parse_toml() {
+		let value = Format::Toml
+			.parse("left = 1\nright = 2.5\n[db]\nhost = \"localhost\"\n")
+			.unwrap();
+		assert_eq!(
+			value,
+			serde_json::json!({ "left": 1, "right": 2.5, "db": { "host": "localhost" } })
+		);
+	}
+
+	#[cfg(feature = "toml")]
+	#[test]
+	fn test_render_toml_round_trip() {
+		let value = serde_json::json!({ "db": { "host": "localhost" }, "left": 1, "ratio": null });
+		let rendered = Format::Toml.render(&value).unwrap();
+		assert_eq!(
+			Format::Toml.parse(&rendered).unwrap(),
+			serde_json::json!({ "db": { "host": "localhost" }, "left": 1 })
+		);
+	}
+
+	#[cfg(feature = "yaml")]
+	#[test]
+	fn test_parse_yaml() {
+		let value = Format::Yaml
+			.parse("base: &base\n  host: localhost\n  port: 1\ndb:\n  <<: *base\n  port: 2\n")
+			.unwrap();
+		assert_eq!(value["db"], serde_json::json!({ "host": "localhost", "port": 2 }));
+
+		let err = Format::Yaml.parse("left: 1\n---\nleft: 2\n").unwrap_err();
+		assert!(err.to_string().contains("multi-document YAML is not supported"));
+	}
+
+	#[cfg(all(feature = "toml", feature = "yaml"))]
+	#[test]
+	fn test_sniff() {
+		assert_eq!(Format::sniff("{\"left\": 1}"), Format::Json);
+		assert_eq!(Format::sniff("left = 1\n[db]\nport = 5432\n"), Format::Toml);
+		assert_eq!(Format::sniff("left: 1\ndb:\n  port: 5432\n"), Format::Yaml);
+	}
+
+	#[test]
+	fn test_document_line_of() {
+		let contents = "{\n  \"left\": 1,\n  \"db\": {\n    \"left\": 2\n  }\n}\n".to_string();
+		let value = Format::Json.parse(&contents).unwrap();
+		let document =
+			Document { path: "config.json".to_string(), format: Format::Json, contents, value };
+
+		assert_eq!(document.line_of(&KeyPath::parse_dotted("left")), Some(2));
+		assert_eq!(document.line_of(&KeyPath::parse_dotted("db.left")), Some(4));
+		assert_eq!(document.line_of(&KeyPath::parse_dotted("right")), None);
+
+		// A nested key of the same name appearing first does not shadow the top level one
+		let contents = "{\n  \"db\": {\"left\": 5},\n  \"left\": 1\n}\n".to_string();
+		let value = Format::Json.parse(&contents).unwrap();
+		let document =
+			Document { path: "config.json".to_string(), format: Format::Json, contents, value };
+		assert_eq!(document.line_of(&KeyPath::parse_dotted("left")), Some(3));
+		assert_eq!(document.line_of(&KeyPath::parse_dotted("db.left")), Some(2));
+	}
+
+	#[cfg(feature = "toml")]
+	#[test]
+	fn test_document_line_of_dotted_toml() {
+		let contents = "[default]\nleft = 1\n\n[profiles.prod]\nleft = 2\n".to_string();
+		let document = Document {
+			path: "config.toml".to_string(),
+			format: Format::Toml,
+			contents,
+			value: Value::Null,
+		};
+
+		assert_eq!(document.line_of(&KeyPath::parse_dotted("default.left")), Some(2));
+		assert_eq!(document.line_of(&KeyPath::parse_dotted("profiles.prod.left")), Some(5));
+	}
+}
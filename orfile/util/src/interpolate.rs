@@ -0,0 +1,265 @@
+use crate::path::KeyPath;
+use crate::Error;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// A `${...}` reference inside a config value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Reference {
+	/// `${NAME}` or `${NAME:-default}`, where the default is used when the variable is unset or empty.
+	Env { name: String, default: Option<String> },
+	/// `${self.db.host}`, a key of the merged config.
+	Key(KeyPath),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+	Text(String),
+	Reference(Reference),
+}
+
+/// Expands the references in the values of `map` which came from config files.
+///
+/// `files` maps the path of each such value to the file it came from, for error messages. Values
+/// from other layers are kept as they are, so env vars and extra args are never expanded. A value
+/// which is a single reference takes the referenced value as is, so `"${self.port}"` stays a
+/// number, while references inside longer strings are rendered as text. `$${` writes a literal
+/// `${`.
+pub fn interpolate(
+	map: &mut Map<String, Value>,
+	files: &BTreeMap<KeyPath, String>,
+	var: impl Fn(&str) -> Option<String>,
+) -> Result<(), Error> {
+	let mut interpolator =
+		Interpolator { map, files, var, resolved: BTreeMap::new(), stack: Vec::new() };
+	for leaf in files.keys() {
+		interpolator.leaf(leaf)?;
+	}
+
+	let resolved = interpolator.resolved;
+	for (leaf, value) in resolved {
+		if leaf.get(map) != Some(&value) {
+			leaf.insert(map, value);
+		}
+	}
+	Ok(())
+}
+
+struct Interpolator<'a, F> {
+	map: &'a Map<String, Value>,
+	files: &'a BTreeMap<KeyPath, String>,
+	var: F,
+	/// The expanded value of each file value visited so far.
+	resolved: BTreeMap<KeyPath, Value>,
+	/// The file values being expanded, to detect circular references.
+	stack: Vec<KeyPath>,
+}
+
+impl<F: Fn(&str) -> Option<String>> Interpolator<'_, F> {
+	fn error(&self, leaf: &KeyPath, reason: String) -> Error {
+		Error::Interpolation {
+			path: self.files.get(leaf).cloned().unwrap_or_default(),
+			key: leaf.to_string(),
+			reason,
+		}
+	}
+
+	fn leaf(&mut self, leaf: &KeyPath) -> Result<Value, Error> {
+		if let Some(value) = self.resolved.get(leaf) {
+			return Ok(value.clone());
+		}
+		if self.stack.contains(leaf) {
+			let cycle: Vec<String> = self.stack.iter().map(ToString::to_string).collect();
+			return Err(self.error(
+				leaf,
+				format!("circular reference through {} and back", cycle.join(" -> ")),
+			));
+		}
+		let Some(raw) = leaf.get(self.map).cloned() else {
+			return Ok(Value::Null);
+		};
+
+		self.stack.push(leaf.clone());
+		let value = self.value(leaf, raw);
+		self.stack.pop();
+
+		let value = value?;
+		self.resolved.insert(leaf.clone(), value.clone());
+		Ok(value)
+	}
+
+	/// Expands the strings of a value, including those inside arrays.
+	fn value(&mut self, leaf: &KeyPath, raw: Value) -> Result<Value, Error> {
+		match raw {
+			Value::String(s) if s.contains('$') => self.string(leaf, &s),
+			Value::Array(items) => items
+				.into_iter()
+				.map(|item| self.value(leaf, item))
+				.collect::<Result<_, _>>()
+				.map(Value::Array),
+			Value::Object(object) => object
+				.into_iter()
+				.map(|(key, value)| Ok((key, self.value(leaf, value)?)))
+				.collect::<Result<_, _>>()
+				.map(Value::Object),
+			value => Ok(value),
+		}
+	}
+
+	fn string(&mut self, leaf: &KeyPath, raw: &str) -> Result<Value, Error> {
+		let parts = parse(raw).map_err(|reason| self.error(leaf, reason))?;
+		if let [Part::Reference(reference)] = parts.as_slice() {
+			return self.reference(leaf, reference);
+		}
+
+		let mut expanded = String::new();
+		for part in parts {
+			match part {
+				Part::Text(text) => expanded.push_str(&text),
+				Part::Reference(reference) => match self.reference(leaf, &reference)? {
+					Value::String(s) => expanded.push_str(&s),
+					value => expanded.push_str(&value.to_string()),
+				},
+			}
+		}
+		Ok(Value::String(expanded))
+	}
+
+	fn reference(&mut self, leaf: &KeyPath, reference: &Reference) -> Result<Value, Error> {
+		match reference {
+			Reference::Env { name, default } => {
+				match ((self.var)(name).filter(|value| !value.is_empty()), default) {
+					(Some(value), _) => Ok(Value::String(value)),
+					(None, Some(default)) => Ok(Value::String(default.clone())),
+					(None, None) => {
+						Err(self.error(leaf, format!("environment variable `{}` is not set", name)))
+					}
+				}
+			}
+			Reference::Key(path) => {
+				// A key inside a file value, such as an array item, is read from the expanded value
+				let file_leaf =
+					self.files.keys().find(|file_leaf| path.starts_with(file_leaf)).cloned();
+				let value = match file_leaf {
+					Some(file_leaf) => {
+						let value = self.leaf(&file_leaf)?;
+						let rest = KeyPath(path.0[file_leaf.0.len()..].to_vec());
+						if rest.is_empty() {
+							Some(value)
+						} else {
+							value.as_object().and_then(|object| rest.get(object)).cloned()
+						}
+					}
+					None => path.get(self.map).cloned(),
+				};
+				value
+					.filter(|value| !value.is_null())
+					.ok_or_else(|| self.error(leaf, format!("key `self.{}` is not set", path)))
+			}
+		}
+	}
+}
+
+/// Splits a string into text and references.
+fn parse(raw: &str) -> Result<Vec<Part>, String> {
+	let mut parts = Vec::new();
+	let mut text = String::new();
+	let mut rest = raw;
+	while let Some(index) = rest.find('$') {
+		text.push_str(&rest[..index]);
+		let from = &rest[index..];
+		if let Some(after) = from.strip_prefix("$${") {
+			text.push_str("${");
+			rest = after;
+		} else if let Some(after) = from.strip_prefix("${") {
+			let end = after.find('}').ok_or_else(|| format!("unclosed `${{` in `{}`", raw))?;
+			if !text.is_empty() {
+				parts.push(Part::Text(std::mem::take(&mut text)));
+			}
+			parts.push(Part::Reference(reference(&after[..end])?));
+			rest = &after[end + 1..];
+		} else {
+			text.push('$');
+			rest = &from[1..];
+		}
+	}
+	text.push_str(rest);
+	if !text.is_empty() {
+		parts.push(Part::Text(text));
+	}
+	Ok(parts)
+}
+
+fn reference(inner: &str) -> Result<Reference, String> {
+	let inner = inner.trim();
+	if let Some(key) = inner.strip_prefix("self.") {
+		let path = KeyPath::parse_dotted(key);
+		if path.is_empty() {
+			return Err("empty key in `${self.}`".to_string());
+		}
+		return Ok(Reference::Key(path));
+	}
+
+	let (name, default) = match inner.split_once(":-") {
+		Some((name, default)) => (name, Some(default.to_string())),
+		None => (inner, None),
+	};
+	if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+		return Err(format!("invalid variable name `{}`", name));
+	}
+	Ok(Reference::Env { name: name.to_string(), default })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	fn files(paths: &[&str]) -> BTreeMap<KeyPath, String> {
+		paths
+			.iter()
+			.map(|path| (KeyPath::parse_dotted(path), "config.json".to_string()))
+			.collect()
+	}
+
+	#[test]
+	fn test_interpolate() {
+		let var = |name: &str| (name == "HOST").then(|| "db.local".to_string());
+		let mut map = json!({
+			"db": { "host": "${HOST}", "port": 5432 },
+			"url": "postgres://${self.db.host}:${self.db.port}/${NAME:-add}",
+			"port": "${self.db.port}",
+			"literal": "$${HOST} costs $5",
+			"from_args": "${HOST}",
+		})
+		.as_object()
+		.unwrap()
+		.clone();
+
+		interpolate(&mut map, &files(&["db.host", "db.port", "url", "port", "literal"]), var)
+			.unwrap();
+		assert_eq!(
+			Value::Object(map),
+			json!({
+				"db": { "host": "db.local", "port": 5432 },
+				"url": "postgres://db.local:5432/add",
+				"port": 5432,
+				"literal": "${HOST} costs $5",
+				"from_args": "${HOST}",
+			})
+		);
+	}
+
+	#[test]
+	fn test_interpolate_errors() {
+		let mut map = json!({ "a": "${MISSING}" }).as_object().unwrap().clone();
+		assert_eq!(
+			interpolate(&mut map, &files(&["a"]), |_| None).unwrap_err().to_string(),
+			"failed to interpolate `a` in config file config.json: environment variable `MISSING` is not set"
+		);
+
+		let mut map = json!({ "a": "${self.b}", "b": "${self.a}" }).as_object().unwrap().clone();
+		let err = interpolate(&mut map, &files(&["a", "b"]), |_| None).unwrap_err();
+		assert!(err.to_string().contains("circular reference"), "{}", err);
+	}
+}
@@ -1 +1,29 @@
+pub mod args;
+pub mod coerce;
+pub mod command;
+pub mod config;
+pub mod discover;
+pub mod env;
+pub mod error;
+pub mod explain;
+pub mod extends;
+pub mod format;
+pub mod interpolate;
+pub mod merge;
+pub mod path;
+pub mod paths;
+pub mod profile;
+pub mod provenance;
+pub mod resolve;
+pub mod schema;
+pub mod span;
+pub mod strict;
+pub mod template;
 
+pub use config::{Field, Kind, MergeStrategy, OrfileConfig};
+pub use error::Error;
+pub use explain::Explanation;
+pub use format::{read_document, read_document_as, read_file, write_file, Document, Format};
+pub use path::KeyPath;
+pub use provenance::{Origin, Provenance, Source};
+pub use resolve::Resolver;
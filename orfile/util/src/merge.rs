@@ -0,0 +1,98 @@
+use crate::coerce::split_list;
+use crate::config::{self, Field, MergeStrategy};
+use serde_json::{Map, Value};
+
+/// Deep merges `overlay` into `base`.
+///
+/// Objects are merged key by key so that a layer only replaces the leaves it sets. Any other value
+/// in `overlay` replaces the value in `base` outright.
+pub fn merge(base: &mut Value, overlay: Value) {
+	merge_field(base, overlay, None);
+}
+
+/// Deep merges the entries of `overlay` into `base`.
+pub fn merge_object(base: &mut Map<String, Value>, overlay: Map<String, Value>) {
+	merge_object_with(base, overlay, &[]);
+}
+
+/// Deep merges the entries of `overlay` into `base`, following the merge strategy of each field.
+pub fn merge_object_with(
+	base: &mut Map<String, Value>,
+	overlay: Map<String, Value>,
+	fields: &[Field],
+) {
+	for (key, value) in overlay {
+		let field = config::find(fields, &key);
+		match base.get_mut(&key) {
+			Some(existing) => merge_field(existing, value, field),
+			None => {
+				base.insert(key, value);
+			}
+		}
+	}
+}
+
+/// Deep merges `overlay` into the value of `field`.
+///
+/// Fields with [MergeStrategy::Append] concatenate the two values as lists. Strings are read as
+/// lists with [split_list], so `ADD_PEERS=a,b` appends two items.
+pub fn merge_field(base: &mut Value, overlay: Value, field: Option<&Field>) {
+	let append = field.is_some_and(|field| field.merge == MergeStrategy::Append);
+	if append && !base.is_null() {
+		let mut items = into_list(std::mem::take(base));
+		items.extend(into_list(overlay));
+		*base = Value::Array(items);
+		return;
+	}
+
+	let nested = field.map(|field| field.nested.as_slice()).unwrap_or_default();
+	match (base, overlay) {
+		(Value::Object(base), Value::Object(overlay)) => merge_object_with(base, overlay, nested),
+		(base, overlay) => *base = overlay,
+	}
+}
+
+fn into_list(value: Value) -> Vec<Value> {
+	match value {
+		Value::Null => Vec::new(),
+		Value::Array(items) => items,
+		Value::String(s) => split_list(&s),
+		value => vec![value],
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn test_merge_nested_objects() {
+		let mut base = json!({ "left": 1, "db": { "host": "localhost", "port": 5432 } });
+		merge(&mut base, json!({ "db": { "port": 6543 }, "right": 2 }));
+
+		assert_eq!(
+			base,
+			json!({ "left": 1, "right": 2, "db": { "host": "localhost", "port": 6543 } })
+		);
+	}
+
+	#[test]
+	fn test_merge_replaces_non_objects() {
+		let mut base = json!({ "db": { "host": "localhost" }, "peers": [1, 2] });
+		merge(&mut base, json!({ "db": "postgres://db", "peers": [3] }));
+
+		assert_eq!(base, json!({ "db": "postgres://db", "peers": [3] }));
+	}
+
+	#[test]
+	fn test_merge_append_field() {
+		let fields =
+			vec![Field::new("peers").with_merge(MergeStrategy::Append), Field::new("tags")];
+		let mut base = json!({ "peers": "a,b", "tags": ["x"] }).as_object().unwrap().clone();
+		let overlay = json!({ "peers": ["c"], "tags": ["y"] }).as_object().unwrap().clone();
+
+		merge_object_with(&mut base, overlay, &fields);
+		assert_eq!(Value::Object(base), json!({ "peers": ["a", "b", "c"], "tags": ["y"] }));
+	}
+}
@@ -0,0 +1,198 @@
+use crate::config::{self, Field};
+use crate::merge::merge_field;
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// One step of a [KeyPath].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Segment {
+	/// A key of an object.
+	Key(String),
+	/// An index into an array.
+	Index(usize),
+}
+
+impl fmt::Display for Segment {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Segment::Key(key) => write!(f, "{}", key),
+			Segment::Index(index) => write!(f, "{}", index),
+		}
+	}
+}
+
+/// A path to a value nested inside a config, written with dots as in `db.port` or `peers.0.url`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct KeyPath(pub Vec<Segment>);
+
+impl KeyPath {
+	/// Builds a path of object keys only.
+	pub fn from_keys<I, S>(keys: I) -> Self
+	where
+		I: IntoIterator<Item = S>,
+		S: Into<String>,
+	{
+		Self(keys.into_iter().map(|key| Segment::Key(key.into())).collect())
+	}
+
+	/// Parses a dotted path, reading numeric segments as array indices.
+	pub fn parse_dotted(dotted: &str) -> Self {
+		Self(
+			dotted
+				.split('.')
+				.filter(|segment| !segment.is_empty())
+				.map(|segment| match segment.parse::<usize>() {
+					Ok(index) => Segment::Index(index),
+					Err(_) => Segment::Key(segment.to_string()),
+				})
+				.collect(),
+		)
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	/// Whether `prefix` is this path or one of its ancestors.
+	pub fn starts_with(&self, prefix: &KeyPath) -> bool {
+		self.0.starts_with(&prefix.0)
+	}
+
+	/// Returns this path nested under `key`.
+	pub fn prefixed(&self, key: &str) -> KeyPath {
+		let mut segments = vec![Segment::Key(key.to_string())];
+		segments.extend(self.0.iter().cloned());
+		KeyPath(segments)
+	}
+
+	/// Looks up the value at this path inside `map`.
+	pub fn get<'a>(&self, map: &'a Map<String, Value>) -> Option<&'a Value> {
+		let (first, rest) = self.0.split_first()?;
+		let Segment::Key(key) = first else {
+			return None;
+		};
+		rest.iter().try_fold(map.get(key)?, |value, segment| match segment {
+			Segment::Key(key) => value.get(key),
+			Segment::Index(index) => value.get(index),
+		})
+	}
+
+	/// Lists the paths of the leaves of `map`, descending into objects but not arrays.
+	pub fn leaves(map: &Map<String, Value>) -> Vec<KeyPath> {
+		fn collect(map: &Map<String, Value>, prefix: &mut Vec<Segment>, leaves: &mut Vec<KeyPath>) {
+			for (key, value) in map {
+				prefix.push(Segment::Key(key.clone()));
+				match value {
+					Value::Object(object) if !object.is_empty() => collect(object, prefix, leaves),
+					_ => leaves.push(KeyPath(prefix.clone())),
+				}
+				prefix.pop();
+			}
+		}
+
+		let mut leaves = Vec::new();
+		collect(map, &mut Vec::new(), &mut leaves);
+		leaves
+	}
+
+	/// Sets `value` at this path inside `map`, creating intermediate objects and arrays as needed.
+	///
+	/// An intermediate value of the wrong shape is replaced, and arrays shorter than an index are
+	/// padded with nulls. Values already present at the path are deep merged with `value`.
+	pub fn insert(&self, map: &mut Map<String, Value>, value: Value) {
+		self.insert_with(map, value, &[]);
+	}
+
+	/// Like [KeyPath::insert], but merges with the strategy of the field the path leads to.
+	pub fn insert_with(&self, map: &mut Map<String, Value>, value: Value, fields: &[Field]) {
+		let mut root = Value::Object(std::mem::take(map));
+		insert_value(&mut root, &self.0, value, fields, None);
+		if let Value::Object(root) = root {
+			*map = root;
+		}
+	}
+}
+
+fn insert_value(
+	target: &mut Value,
+	path: &[Segment],
+	value: Value,
+	fields: &[Field],
+	field: Option<&Field>,
+) {
+	let Some((first, rest)) = path.split_first() else {
+		merge_field(target, value, field);
+		return;
+	};
+
+	// Items of an array share the nested fields of the array field itself
+	let (slot, fields, field) = match first {
+		Segment::Key(key) => {
+			if !target.is_object() {
+				*target = Value::Object(Map::new());
+			}
+			let object = target.as_object_mut().expect("target was just made an object");
+			let field = config::find(fields, key);
+			let nested = field.map(|field| field.nested.as_slice()).unwrap_or_default();
+			(object.entry(key.clone()).or_insert(Value::Null), nested, field)
+		}
+		Segment::Index(index) => {
+			if !target.is_array() {
+				*target = Value::Array(Vec::new());
+			}
+			let array = target.as_array_mut().expect("target was just made an array");
+			if array.len() <= *index {
+				array.resize(*index + 1, Value::Null);
+			}
+			(&mut array[*index], fields, None)
+		}
+	};
+
+	// A fresh slot takes the value as is rather than merging into the null placeholder
+	if slot.is_null() && rest.is_empty() {
+		*slot = value;
+	} else {
+		insert_value(slot, rest, value, fields, field);
+	}
+}
+
+impl fmt::Display for KeyPath {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (i, segment) in self.0.iter().enumerate() {
+			if i > 0 {
+				write!(f, ".")?;
+			}
+			write!(f, "{}", segment)?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn test_insert_nested_keys() {
+		let mut map = Map::new();
+		KeyPath::from_keys(["db", "host"]).insert(&mut map, json!("localhost"));
+		KeyPath::parse_dotted("db.port").insert(&mut map, json!("5432"));
+
+		assert_eq!(Value::Object(map), json!({ "db": { "host": "localhost", "port": "5432" } }));
+	}
+
+	#[test]
+	fn test_insert_array_index() {
+		let mut map =
+			json!({ "peers": [{ "url": "a", "weight": 1 }] }).as_object().unwrap().clone();
+		KeyPath::parse_dotted("peers.0.url").insert(&mut map, json!("b"));
+		KeyPath::parse_dotted("peers.2.url").insert(&mut map, json!("c"));
+
+		assert_eq!(
+			Value::Object(map),
+			json!({ "peers": [{ "url": "b", "weight": 1 }, null, { "url": "c" }] })
+		);
+		assert_eq!(KeyPath::parse_dotted("peers.0.url").to_string(), "peers.0.url");
+	}
+}
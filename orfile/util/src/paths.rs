@@ -0,0 +1,84 @@
+use crate::config::Field;
+use crate::path::KeyPath;
+use std::path::{Path, PathBuf};
+
+/// Lists the paths of the fields marked `#[orfile(path)]`, including those of nested config types.
+pub fn fields(fields: &[Field]) -> Vec<KeyPath> {
+	let mut paths = Vec::new();
+	for field in fields {
+		let path = KeyPath::from_keys([field.name]);
+		if field.path {
+			paths.push(path.clone());
+		}
+		for nested in self::fields(&field.nested) {
+			paths.push(KeyPath([path.0.clone(), nested.0].concat()));
+		}
+	}
+	paths
+}
+
+/// Resolves the value of a path field into an absolute path.
+///
+/// A leading `~` expands to `home`. Other relative paths which came from the config file at `file`
+/// are joined to the directory of the file, and those from env vars and args to `cwd`. The result
+/// is absolute so a saved resolved config means the same wherever it is replayed from. A `~` which
+/// cannot be expanded is kept as is.
+pub fn resolve(raw: &str, file: Option<&str>, home: Option<&str>, cwd: &Path) -> String {
+	let expanded = match (raw.strip_prefix('~'), home) {
+		(Some(""), Some(home)) => PathBuf::from(home),
+		(Some(rest), Some(home)) if rest.starts_with('/') => {
+			Path::new(home).join(rest.trim_start_matches('/'))
+		}
+		(Some(_), _) => return raw.to_string(),
+		(None, _) => PathBuf::from(raw),
+	};
+	if expanded.is_absolute() {
+		return expanded.display().to_string();
+	}
+
+	let dir = match file.and_then(|file| Path::new(file).parent()) {
+		Some(dir) => cwd.join(dir),
+		None => cwd.to_path_buf(),
+	};
+	// Collecting the components drops `.` segments such as a leading `./` in the value
+	dir.join(expanded).components().collect::<PathBuf>().display().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_resolve() {
+		let home = Some("/home/node");
+		let cwd = Path::new("/work");
+		assert_eq!(
+			resolve("./keys/node.pem", Some("conf/add.toml"), home, cwd),
+			"/work/conf/keys/node.pem"
+		);
+		assert_eq!(
+			resolve("../node.pem", Some("/etc/tool/add.toml"), home, cwd),
+			"/etc/tool/../node.pem"
+		);
+		assert_eq!(resolve("keys/node.pem", Some("add.toml"), home, cwd), "/work/keys/node.pem");
+		assert_eq!(resolve("./keys/node.pem", None, home, cwd), "/work/keys/node.pem");
+		assert_eq!(resolve("/keys/node.pem", Some("conf/add.toml"), home, cwd), "/keys/node.pem");
+		assert_eq!(
+			resolve("~/keys/node.pem", Some("conf/add.toml"), home, cwd),
+			"/home/node/keys/node.pem"
+		);
+		assert_eq!(resolve("~", None, home, cwd), "/home/node");
+		assert_eq!(resolve("~other/node.pem", None, home, cwd), "~other/node.pem");
+		assert_eq!(resolve("~/node.pem", None, None, cwd), "~/node.pem");
+	}
+
+	#[test]
+	fn test_fields() {
+		let fields = vec![
+			Field::new("key_file").path(),
+			Field::new("db").with_nested(vec![Field::new("host"), Field::new("cert").path()]),
+		];
+		let paths: Vec<String> = self::fields(&fields).iter().map(ToString::to_string).collect();
+		assert_eq!(paths, vec!["key_file", "db.cert"]);
+	}
+}
@@ -0,0 +1,70 @@
+use crate::path::KeyPath;
+use serde_json::{Map, Value};
+
+/// The section of a profiled config file which every profile is merged over.
+pub const DEFAULT: &str = "default";
+/// The section of a profiled config file holding the named profiles.
+pub const PROFILES: &str = "profiles";
+
+/// Whether a config file uses the profile layout, `{"default": {...}, "profiles": {"prod": {...}}}`.
+///
+/// A file only uses it when its top level keys are `default` and `profiles`, ignoring `$schema`,
+/// and neither is a field of the config type, so configs with such fields keep their meaning.
+pub fn is_profiled(map: &Map<String, Value>, is_field: impl Fn(&str) -> bool) -> bool {
+	let sections = [DEFAULT, PROFILES];
+	map.keys().any(|key| sections.contains(&key.as_str()))
+		&& map.keys().all(|key| sections.contains(&key.as_str()) || key == "$schema")
+		&& !sections.iter().any(|section| is_field(section))
+}
+
+/// The names of the profiles defined in a profiled config file.
+pub fn names(map: &Map<String, Value>) -> Vec<String> {
+	match map.get(PROFILES) {
+		Some(Value::Object(profiles)) => profiles.keys().cloned().collect(),
+		_ => Vec::new(),
+	}
+}
+
+/// Lists the sections of a profiled config file to merge, in order, with their paths in the file.
+///
+/// The `default` section comes first and the selected profile, if the file defines it, second.
+pub fn sections<'a>(
+	map: &'a Map<String, Value>,
+	profile: Option<&str>,
+) -> Vec<(KeyPath, &'a Map<String, Value>)> {
+	let mut sections = Vec::new();
+	if let Some(Value::Object(default)) = map.get(DEFAULT) {
+		sections.push((KeyPath::from_keys([DEFAULT]), default));
+	}
+	if let (Some(profile), Some(Value::Object(profiles))) = (profile, map.get(PROFILES)) {
+		if let Some(Value::Object(section)) = profiles.get(profile) {
+			sections.push((KeyPath::from_keys([PROFILES, profile]), section));
+		}
+	}
+	sections
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn test_profile_sections() {
+		let file = json!({
+			"default": { "left": 1 },
+			"profiles": { "prod": { "right": 2 }, "dev": {} },
+		});
+		let map = file.as_object().unwrap();
+
+		assert!(is_profiled(map, |_| false));
+		assert!(!is_profiled(map, |key| key == "default"));
+		assert!(!is_profiled(json!({ "left": 1 }).as_object().unwrap(), |_| false));
+		assert_eq!(names(map), vec!["dev", "prod"]);
+
+		let paths: Vec<String> =
+			sections(map, Some("prod")).iter().map(|(path, _)| path.to_string()).collect();
+		assert_eq!(paths, vec!["default", "profiles.prod"]);
+		assert_eq!(sections(map, Some("staging")).len(), 1);
+	}
+}
@@ -0,0 +1,121 @@
+use crate::path::KeyPath;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Where a resolved config value came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+	/// An environment variable.
+	Env { name: String },
+	/// A config file, with the 1-based line of the value when it could be located.
+	File { path: String, line: Option<usize> },
+	/// A flag among the extra args, with the 1-based position of each occurrence.
+	Arg { flag: String, positions: Vec<usize> },
+}
+
+impl fmt::Display for Source {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Source::Env { name } => write!(f, "env {}", name),
+			Source::File { path, line: Some(line) } => write!(f, "file {}:{}", path, line),
+			Source::File { path, line: None } => write!(f, "file {}", path),
+			Source::Arg { flag, positions } => {
+				let positions: Vec<_> = positions.iter().map(ToString::to_string).collect();
+				write!(f, "arg {} at position {}", flag, positions.join(", "))
+			}
+		}
+	}
+}
+
+/// The source of a resolved value and the lower layers it took precedence over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+	/// The layer which set the value last.
+	pub source: Source,
+	/// Earlier layers which also set this path (or a parent or child of it), lowest first.
+	///
+	/// For fields with `#[orfile(merge = "append")]` these layers were extended rather than replaced.
+	pub overridden: Vec<Source>,
+}
+
+/// Maps each resolved config path to its [Origin].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Provenance {
+	origins: BTreeMap<KeyPath, Origin>,
+}
+
+impl Provenance {
+	/// Records that `source` set the value at `path`.
+	///
+	/// Any origins recorded for the path, its parents or its children are folded into the
+	/// overridden layers of the new origin.
+	pub fn record(&mut self, path: KeyPath, source: Source) {
+		let mut overridden = Vec::new();
+		self.origins.retain(|existing, origin| {
+			if existing.starts_with(&path) || path.starts_with(existing) {
+				overridden.append(&mut origin.overridden);
+				overridden.push(origin.source.clone());
+				false
+			} else {
+				true
+			}
+		});
+		self.origins.insert(path, Origin { source, overridden });
+	}
+
+	pub fn get(&self, path: &KeyPath) -> Option<&Origin> {
+		self.origins.get(path)
+	}
+
+	/// Iterates over the origins in path order.
+	pub fn iter(&self) -> impl Iterator<Item = (&KeyPath, &Origin)> {
+		self.origins.iter()
+	}
+
+	pub fn len(&self) -> usize {
+		self.origins.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.origins.is_empty()
+	}
+
+	/// Nests every path under `key`, as when a config is one field of a larger struct.
+	pub fn prefixed(self, key: &str) -> Self {
+		Self {
+			origins: self
+				.origins
+				.into_iter()
+				.map(|(path, origin)| (path.prefixed(key), origin))
+				.collect(),
+		}
+	}
+
+	/// Adds the origins of `other`, replacing any for the same paths.
+	pub fn extend(&mut self, other: Provenance) {
+		self.origins.extend(other.origins);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_record_overrides() {
+		let env = Source::Env { name: "ADD_DB__PORT".to_string() };
+		let file = Source::File { path: "config.json".to_string(), line: Some(3) };
+		let arg = Source::Arg { flag: "--db".to_string(), positions: vec![1] };
+
+		let mut provenance = Provenance::default();
+		provenance.record(KeyPath::parse_dotted("db.port"), env.clone());
+		provenance.record(KeyPath::parse_dotted("db.port"), file.clone());
+		provenance.record(KeyPath::parse_dotted("db"), arg.clone());
+
+		assert_eq!(provenance.len(), 1);
+		assert_eq!(
+			provenance.get(&KeyPath::parse_dotted("db")),
+			Some(&Origin { source: arg, overridden: vec![env, file] })
+		);
+	}
+}
@@ -0,0 +1,306 @@
+use crate::args::{self, Assignment};
+use crate::coerce;
+use crate::config::{self, Field};
+use crate::env::{self, Variable};
+use crate::format::Document;
+use crate::merge::merge_object_with;
+use crate::path::{KeyPath, Segment};
+use crate::profile;
+use crate::provenance::{Provenance, Source};
+use crate::{interpolate, paths, strict, Error};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+
+/// Merges the `using` layers for one config type while tracking where each value came from.
+///
+/// Layers are merged in the order they are added, each deep merged over the ones before it.
+#[derive(Debug, Clone)]
+pub struct Resolver {
+	fields: Vec<Field>,
+	map: Map<String, Value>,
+	provenance: Provenance,
+	/// The profile selected from profiled config files.
+	profile: Option<String>,
+	/// The profiles defined by the profiled config files merged so far.
+	profiles: BTreeSet<String>,
+	/// Whether a merged config file defined the selected profile.
+	profile_found: bool,
+}
+
+impl Resolver {
+	pub fn new(fields: Vec<Field>) -> Self {
+		Self {
+			fields,
+			map: Map::new(),
+			provenance: Provenance::default(),
+			profile: None,
+			profiles: BTreeSet::new(),
+			profile_found: false,
+		}
+	}
+
+	/// Selects the profile merged over the `default` section of profiled config files.
+	pub fn set_profile(&mut self, profile: Option<String>) {
+		self.profile = profile;
+	}
+
+	/// Merges the process environment variables starting with `prefix`, along with any variables
+	/// bound to fields with `#[orfile(env = "...")]`.
+	///
+	/// `<prefix>PROFILE` selects a profile rather than setting a value, unless `profile` is a field.
+	/// A profile selected with [Resolver::set_profile] takes precedence over it.
+	pub fn merge_env(&mut self, prefix: &str) {
+		let selects_profile = config::find(&self.fields, "profile").is_none();
+		if selects_profile && self.profile.is_none() {
+			self.profile = std::env::var(format!("{}PROFILE", prefix)).ok();
+		}
+		let variables = env::variables_for(prefix, &self.fields, std::env::vars())
+			.into_iter()
+			.filter(|variable| {
+				!(selects_profile && variable.path == KeyPath::from_keys(["profile"]))
+			})
+			.collect();
+		self.merge_variables(variables);
+	}
+
+	/// Merges environment variables.
+	pub fn merge_variables(&mut self, variables: Vec<Variable>) {
+		for Variable { name, path, value } in variables {
+			path.insert_with(&mut self.map, Value::String(value), &self.fields);
+			self.provenance.record(path, Source::Env { name });
+		}
+	}
+
+	/// Merges a config file, accepting field aliases as keys. Documents which are not objects are ignored.
+	///
+	/// A top level `$schema` key only points editors at the schema of the file and is dropped. Files
+	/// using the profile layout have their `default` section merged, then the selected profile.
+	pub fn merge_document(&mut self, document: &Document) {
+		let Value::Object(map) = &document.value else {
+			return;
+		};
+		let mut map = map.clone();
+		map.remove("$schema");
+
+		if !profile::is_profiled(&map, |key| config::find_alias(&self.fields, key).is_some()) {
+			self.merge_section(document, &KeyPath::default(), map);
+			return;
+		}
+
+		self.profiles.extend(profile::names(&map));
+		for (section_path, section) in profile::sections(&map, self.profile.as_deref()) {
+			if section_path.0.len() > 1 {
+				self.profile_found = true;
+			}
+			self.merge_section(document, &section_path, section.clone());
+		}
+	}
+
+	/// Merges the part of a config file found at `section_path` in it.
+	fn merge_section(
+		&mut self,
+		document: &Document,
+		section_path: &KeyPath,
+		map: Map<String, Value>,
+	) {
+		for leaf in KeyPath::leaves(&map) {
+			let mut in_file = section_path.clone();
+			in_file.0.extend(leaf.0.iter().cloned());
+			let line = document.line_of(&in_file);
+			let path = config::canonical_path(&self.fields, &leaf);
+			self.provenance.record(path, Source::File { path: document.path.clone(), line });
+		}
+		merge_object_with(&mut self.map, config::canonicalize(&self.fields, map), &self.fields);
+	}
+
+	/// Checks that the selected profile was defined by one of the merged config files.
+	pub fn check_profile(&self) -> Result<(), Error> {
+		match &self.profile {
+			Some(profile) if !self.profile_found => Err(Error::UnknownProfile {
+				profile: profile.clone(),
+				available: self.profiles.iter().cloned().collect(),
+			}),
+			_ => Ok(()),
+		}
+	}
+
+	/// Applies the assignments parsed from extra args, accepting field aliases as keys.
+	pub fn apply_args(&mut self, assignments: Vec<Assignment>) {
+		let assignments = assignments
+			.into_iter()
+			.map(|assignment| Assignment {
+				path: config::canonical_path(&self.fields, &assignment.path),
+				..assignment
+			})
+			.collect();
+		for (path, value, group) in args::group(assignments, &self.fields) {
+			path.insert_with(&mut self.map, value, &self.fields);
+			let flag = group[0].flag.clone();
+			let positions = group.iter().map(|assignment| assignment.position).collect();
+			self.provenance.record(path, Source::Arg { flag, positions });
+		}
+	}
+
+	/// Expands `${VAR}`, `${VAR:-default}` and `${self.key}` references in values from config files.
+	///
+	/// Run this once every layer is merged, so references see the values which take precedence.
+	pub fn interpolate(&mut self) -> Result<(), Error> {
+		let files = self
+			.provenance
+			.iter()
+			.filter_map(|(path, origin)| match &origin.source {
+				Source::File { path: file, .. } => Some((path.clone(), file.clone())),
+				_ => None,
+			})
+			.collect();
+		interpolate::interpolate(&mut self.map, &files, |name| std::env::var(name).ok())
+	}
+
+	/// Resolves the values of fields marked `#[orfile(path)]` with [paths::resolve].
+	///
+	/// Run this after [Resolver::interpolate], so expanded values are resolved too.
+	pub fn resolve_paths(&mut self) {
+		let home = std::env::var("HOME").ok();
+		let cwd = std::env::current_dir().unwrap_or_default();
+		let mut resolved = Vec::new();
+		for path in paths::fields(&self.fields) {
+			let values = match path.get(&self.map) {
+				Some(Value::String(raw)) => vec![(path.clone(), raw.as_str())],
+				Some(Value::Array(items)) => items
+					.iter()
+					.enumerate()
+					.filter_map(|(index, item)| {
+						let mut item_path = path.clone();
+						item_path.0.push(Segment::Index(index));
+						item.as_str().map(|raw| (item_path, raw))
+					})
+					.collect(),
+				_ => Vec::new(),
+			};
+			for (value_path, raw) in values {
+				let value = paths::resolve(raw, self.file_of(&value_path), home.as_deref(), &cwd);
+				resolved.push((value_path, Value::String(value)));
+			}
+		}
+		for (path, value) in resolved {
+			path.insert(&mut self.map, value);
+		}
+	}
+
+	/// The config file which set the value at `path`, as recorded for it or its closest parent.
+	///
+	/// When an arg set another item of the same array, the array's origin was folded into the
+	/// item's overridden layers, and the layer just below the item is the one which set the array.
+	fn file_of(&self, path: &KeyPath) -> Option<&str> {
+		let source = match self
+			.provenance
+			.iter()
+			.filter(|(recorded, _)| path.starts_with(recorded))
+			.last()
+		{
+			Some((_, origin)) => &origin.source,
+			None => {
+				let parent = KeyPath(path.0[..path.0.len().saturating_sub(1)].to_vec());
+				let (_, sibling) =
+					self.provenance.iter().find(|(recorded, _)| recorded.starts_with(&parent))?;
+				sibling.overridden.last()?
+			}
+		};
+		match source {
+			Source::File { path, .. } => Some(path),
+			_ => None,
+		}
+	}
+
+	/// Checks that every merged key is one of `known`, as `#[orfile(strict)]` requires.
+	///
+	/// Env vars and extra args are merged for every config field of a struct, so `known` holds the
+	/// fields of all of them rather than only those of this config type.
+	pub fn check_strict(&self, known: &[Field]) -> Result<(), Error> {
+		strict::check(&self.map, known, &self.provenance)
+	}
+
+	/// The merged config so far.
+	pub fn merged(&self) -> &Map<String, Value> {
+		&self.map
+	}
+
+	pub fn provenance(&self) -> &Provenance {
+		&self.provenance
+	}
+
+	/// Deserializes the merged config, returning it with its provenance.
+	pub fn finish<T: DeserializeOwned>(self) -> Result<(T, Provenance), serde_json::Error> {
+		let value = coerce::from_value(Value::Object(self.map))?;
+		Ok((value, self.provenance))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::format::Format;
+	use serde_json::json;
+
+	#[test]
+	fn test_resolver_tracks_sources() {
+		let mut resolver = Resolver::new(Vec::new());
+		resolver.merge_variables(env::variables_from(
+			"ADD_",
+			[("ADD_LEFT".to_string(), "1".to_string()), ("ADD_RIGHT".to_string(), "2".to_string())],
+		));
+
+		let contents = "{\n  \"right\": 3\n}".to_string();
+		let value = Format::Json.parse(&contents).unwrap();
+		resolver.merge_document(&Document {
+			path: "config.json".to_string(),
+			format: Format::Json,
+			contents,
+			value,
+		});
+		resolver.apply_args(args::parse(&["--right".to_string(), "4".to_string()]).unwrap());
+
+		let (value, provenance): (Value, _) = resolver.finish().unwrap();
+		assert_eq!(value, json!({ "left": "1", "right": "4" }));
+		assert_eq!(
+			provenance.get(&KeyPath::parse_dotted("left")).unwrap().source,
+			Source::Env { name: "ADD_LEFT".to_string() }
+		);
+
+		let right = provenance.get(&KeyPath::parse_dotted("right")).unwrap();
+		assert_eq!(right.source, Source::Arg { flag: "--right".to_string(), positions: vec![1] });
+		assert_eq!(
+			right.overridden,
+			vec![
+				Source::Env { name: "ADD_RIGHT".to_string() },
+				Source::File { path: "config.json".to_string(), line: Some(2) },
+			]
+		);
+	}
+
+	#[test]
+	fn test_resolved_paths_replay() {
+		let fields = vec![Field::new("key_file").path()];
+		let document = |path: &str, value: Value| Document {
+			path: path.to_string(),
+			format: Format::Json,
+			contents: value.to_string(),
+			value,
+		};
+
+		let mut resolver = Resolver::new(fields.clone());
+		resolver.merge_document(&document("conf/a.json", json!({ "key_file": "k.pem" })));
+		resolver.resolve_paths();
+		let (resolved, _): (Value, _) = resolver.finish().unwrap();
+		let cwd = std::env::current_dir().unwrap();
+		assert_eq!(resolved, json!({ "key_file": cwd.join("conf/k.pem").display().to_string() }));
+
+		// Replaying the saved config from another directory resolves to the same path
+		let mut resolver = Resolver::new(fields);
+		resolver.merge_document(&document("/tmp/replay/r.json", resolved.clone()));
+		resolver.resolve_paths();
+		let (replayed, _): (Value, _) = resolver.finish().unwrap();
+		assert_eq!(replayed, resolved);
+	}
+}
@@ -0,0 +1,294 @@
+use crate::config::{Field, Kind, OrfileConfig};
+use crate::extends::EXTENDS;
+use crate::format::Document;
+use crate::path::{KeyPath, Segment};
+use crate::profile;
+use crate::Error;
+use serde_json::{json, Map, Value};
+
+/// The JSON Schema dialect of the generated schemas.
+pub const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Generates the JSON Schema of a config type from its fields.
+///
+/// Aliases are listed as deprecated properties. With `strict`, objects described by fields reject
+/// other keys, as `#[orfile(strict)]` does. A top level `$schema` key is always allowed so config
+/// files can point editors at the schema, and so is `$extends`.
+pub fn for_config<T: OrfileConfig>(strict: bool) -> Value {
+	let mut schema = object(&T::fields(), strict);
+	schema["$schema"] = json!(DIALECT);
+	if let Some(description) = T::description() {
+		schema["description"] = json!(description);
+	}
+	schema["properties"]["$schema"] = json!({ "type": "string" });
+	schema["properties"][EXTENDS] = json!({
+		"type": ["string", "array"],
+		"items": { "type": "string" },
+	});
+	schema
+}
+
+fn object(fields: &[Field], strict: bool) -> Value {
+	let mut properties = Map::new();
+	let mut required = Vec::new();
+	for field in fields {
+		let schema = field_schema(field, strict);
+		for &alias in &field.aliases {
+			let mut alias_schema = schema.clone();
+			alias_schema["deprecated"] = json!(true);
+			properties.insert(alias.to_string(), alias_schema);
+		}
+		properties.insert(field.name.to_string(), schema);
+		if field.required {
+			required.push(json!(field.name));
+		}
+	}
+
+	let mut schema = json!({ "type": "object", "properties": properties });
+	if !required.is_empty() {
+		schema["required"] = Value::Array(required);
+	}
+	if strict {
+		schema["additionalProperties"] = json!(false);
+	}
+	schema
+}
+
+fn field_schema(field: &Field, strict: bool) -> Value {
+	let kind = match &field.kind {
+		Kind::Any if !field.nested.is_empty() => &Kind::Object,
+		kind => kind,
+	};
+	let mut schema = kind_schema(kind, &field.nested, strict);
+	if let Some(description) = field.description {
+		schema["description"] = json!(description);
+	}
+	schema
+}
+
+fn kind_schema(kind: &Kind, nested: &[Field], strict: bool) -> Value {
+	match kind {
+		Kind::Any => json!({}),
+		Kind::Boolean => json!({ "type": "boolean" }),
+		Kind::Integer { unsigned: false } => json!({ "type": "integer" }),
+		Kind::Integer { unsigned: true } => json!({ "type": "integer", "minimum": 0 }),
+		Kind::Number => json!({ "type": "number" }),
+		Kind::String => json!({ "type": "string" }),
+		Kind::Array(items) => {
+			json!({ "type": "array", "items": kind_schema(items, nested, strict) })
+		}
+		Kind::Object if nested.is_empty() => json!({ "type": "object" }),
+		Kind::Object => object(nested, strict),
+		Kind::Option(inner) => {
+			let mut schema = kind_schema(inner, nested, strict);
+			if let Some(Value::String(ty)) = schema.get("type") {
+				schema["type"] = json!([ty, "null"]);
+			}
+			schema
+		}
+	}
+}
+
+/// A value which does not match a schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+	/// The path of the offending value.
+	pub path: KeyPath,
+	pub reason: String,
+}
+
+/// Checks a value against the subset of JSON Schema generated by [for_config].
+///
+/// A `partial` value is one layer of the config, so its required keys may be set by other layers
+/// and are not checked, and strings holding a `${...}` reference are not checked either since their
+/// type is only known once the reference is expanded.
+pub fn validate(value: &Value, schema: &Value, partial: bool) -> Result<(), Violation> {
+	check(value, schema, partial, &KeyPath::default())
+}
+
+/// Checks a config file against a schema, reporting the key and line of the first violation.
+///
+/// Files using the profile layout have their `default` section and every profile checked.
+pub fn validate_document(document: &Document, schema: &Value) -> Result<(), Error> {
+	let properties = schema.get("properties").and_then(Value::as_object);
+	let is_field = |key: &str| properties.is_some_and(|properties| properties.contains_key(key));
+
+	let sections = match &document.value {
+		Value::Object(map) if profile::is_profiled(map, is_field) => {
+			let mut sections = Vec::new();
+			if let Some(default) = map.get(profile::DEFAULT) {
+				sections.push((KeyPath::from_keys([profile::DEFAULT]), default));
+			}
+			if let Some(Value::Object(profiles)) = map.get(profile::PROFILES) {
+				for (name, section) in profiles {
+					sections.push((KeyPath::from_keys([profile::PROFILES, name]), section));
+				}
+			}
+			sections
+		}
+		value => vec![(KeyPath::default(), value)],
+	};
+
+	for (section_path, section) in sections {
+		validate(section, schema, true).map_err(|violation| {
+			let mut path = section_path.clone();
+			path.0.extend(violation.path.0);
+			Error::Schema {
+				path: document.path.clone(),
+				key: path.to_string(),
+				line: document.line_of(&path),
+				reason: violation.reason,
+			}
+		})?;
+	}
+	Ok(())
+}
+
+fn check(value: &Value, schema: &Value, partial: bool, path: &KeyPath) -> Result<(), Violation> {
+	let violation = |reason: String| Violation { path: path.clone(), reason };
+	if partial && value.as_str().is_some_and(|s| s.replace("$${", "").contains("${")) {
+		return Ok(());
+	}
+
+	let types: Vec<&str> = match schema.get("type") {
+		Some(Value::String(ty)) => vec![ty.as_str()],
+		Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+		_ => Vec::new(),
+	};
+	if !types.is_empty() && !types.iter().any(|ty| has_type(value, ty)) {
+		return Err(violation(format!(
+			"expected {}, found {}",
+			types.join(" or "),
+			type_of(value)
+		)));
+	}
+
+	if let (Some(minimum), Some(number)) =
+		(schema.get("minimum").and_then(Value::as_f64), value.as_f64())
+	{
+		if number < minimum {
+			return Err(violation(format!("expected at least {}, found {}", minimum, number)));
+		}
+	}
+
+	match value {
+		Value::Object(object) => {
+			let properties = schema.get("properties").and_then(Value::as_object);
+			for (key, value) in object {
+				let mut path = path.clone();
+				path.0.push(Segment::Key(key.clone()));
+				match properties.and_then(|properties| properties.get(key)) {
+					Some(schema) => check(value, schema, partial, &path)?,
+					None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+						return Err(Violation { path, reason: "unknown key".to_string() });
+					}
+					None => {}
+				}
+			}
+
+			if !partial {
+				let required = schema.get("required").and_then(Value::as_array);
+				for key in required.into_iter().flatten().filter_map(Value::as_str) {
+					if !object.contains_key(key) {
+						return Err(violation(format!("missing required key `{}`", key)));
+					}
+				}
+			}
+		}
+		Value::Array(items) => {
+			if let Some(schema) = schema.get("items") {
+				for (index, item) in items.iter().enumerate() {
+					let mut path = path.clone();
+					path.0.push(Segment::Index(index));
+					check(item, schema, partial, &path)?;
+				}
+			}
+		}
+		_ => {}
+	}
+	Ok(())
+}
+
+fn has_type(value: &Value, ty: &str) -> bool {
+	match ty {
+		"null" => value.is_null(),
+		"boolean" => value.is_boolean(),
+		"integer" => value.is_i64() || value.is_u64(),
+		"number" => value.is_number(),
+		"string" => value.is_string(),
+		"array" => value.is_array(),
+		"object" => value.is_object(),
+		_ => true,
+	}
+}
+
+fn type_of(value: &Value) -> &'static str {
+	match value {
+		Value::Null => "null",
+		Value::Bool(_) => "boolean",
+		Value::Number(n) if n.is_f64() => "number",
+		Value::Number(_) => "integer",
+		Value::String(_) => "string",
+		Value::Array(_) => "array",
+		Value::Object(_) => "object",
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::config::fixture::Args;
+
+	#[test]
+	fn test_schema_for_config() {
+		let port = json!({ "type": "integer", "minimum": 0 });
+		assert_eq!(
+			for_config::<Args>(true),
+			json!({
+				"$schema": DIALECT,
+				"description": "The arguments",
+				"type": "object",
+				"properties": {
+					"$schema": { "type": "string" },
+					"$extends": { "type": ["string", "array"], "items": { "type": "string" } },
+					"left": { "type": "integer", "minimum": 0, "description": "The left number" },
+					"name": { "type": "string" },
+					"ratio": { "type": ["number", "null"] },
+					"peers": { "type": "array", "items": { "type": "string" } },
+					"db": {
+						"type": "object",
+						"properties": { "p": { "deprecated": true, "type": "integer", "minimum": 0 }, "port": port },
+						"required": ["port"],
+						"additionalProperties": false,
+					},
+				},
+				"required": ["left", "db"],
+				"additionalProperties": false,
+			})
+		);
+	}
+
+	#[test]
+	fn test_validate() {
+		let schema = for_config::<Args>(true);
+		assert!(validate(&json!({ "peers": ["a"], "ratio": null }), &schema, true).is_ok());
+
+		let violation = validate(&json!({ "peers": ["a", 1] }), &schema, true).unwrap_err();
+		assert_eq!(violation.path.to_string(), "peers.1");
+		assert_eq!(violation.reason, "expected string, found integer");
+
+		let violation = validate(&json!({ "db": { "prot": 1 } }), &schema, true).unwrap_err();
+		assert_eq!(violation.path.to_string(), "db.prot");
+
+		let violation = validate(&json!({ "left": -1 }), &schema, true).unwrap_err();
+		assert_eq!(violation.reason, "expected at least 0, found -1");
+
+		// References are checked once expanded, while `$${` is a literal string
+		assert!(validate(&json!({ "left": "${LEFT}" }), &schema, true).is_ok());
+		let violation = validate(&json!({ "left": "$${LEFT}" }), &schema, true).unwrap_err();
+		assert_eq!(violation.reason, "expected integer, found string");
+
+		let violation = validate(&json!({}), &schema, false).unwrap_err();
+		assert_eq!(violation.reason, "missing required key `left`");
+	}
+}
@@ -0,0 +1,305 @@
+use crate::format::Format;
+use crate::path::{KeyPath, Segment};
+
+/// Finds the 1-based line on which the value at `path` is set in the text of a config file.
+///
+/// Each key of the path is looked up inside the value of the previous one, so a key shadowed by a
+/// nested key of the same name resolves to the right line, and so do the keys of inline objects
+/// and the items of arrays. Returns `None` if the path is not set or the text does not parse.
+pub fn line_of(format: Format, contents: &str, path: &KeyPath) -> Option<usize> {
+	if path.is_empty() {
+		return None;
+	}
+	match format {
+		Format::Json => {
+			let offset = Json { text: contents.as_bytes(), pos: 0 }.find(&path.0)?;
+			Some(contents[..offset].matches('\n').count() + 1)
+		}
+		Format::Toml => toml_line(contents, path),
+		Format::Yaml => {
+			let lines: Vec<Line> = contents
+				.lines()
+				.enumerate()
+				.filter_map(|(index, text)| {
+					let trimmed = text.trim_start();
+					let skipped = trimmed.is_empty()
+						|| trimmed.starts_with('#')
+						|| trimmed.starts_with("---");
+					(!skipped).then(|| Line {
+						number: index + 1,
+						indent: text.len() - trimmed.len(),
+						text: trimmed,
+					})
+				})
+				.collect();
+			yaml_line(&lines, &path.0)
+		}
+	}
+}
+
+/// A scanner over JSON text which follows a path through its objects and arrays.
+struct Json<'a> {
+	text: &'a [u8],
+	pos: usize,
+}
+
+impl Json<'_> {
+	fn peek(&mut self) -> Option<u8> {
+		while self.text.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+			self.pos += 1;
+		}
+		self.text.get(self.pos).copied()
+	}
+
+	/// Returns the offset of the key or item at `path` inside the value at the current position.
+	fn find(&mut self, path: &[Segment]) -> Option<usize> {
+		let (segment, rest) = path.split_first()?;
+		match (self.peek()?, segment) {
+			(b'{', Segment::Key(key)) => {
+				self.pos += 1;
+				loop {
+					match self.peek()? {
+						b'}' => return None,
+						b',' => self.pos += 1,
+						_ => {
+							let start = self.pos;
+							let name = self.string()?;
+							if self.peek()? != b':' {
+								return None;
+							}
+							self.pos += 1;
+							if &name == key {
+								return if rest.is_empty() { Some(start) } else { self.find(rest) };
+							}
+							self.skip_value()?;
+						}
+					}
+				}
+			}
+			(b'[', Segment::Index(index)) => {
+				self.pos += 1;
+				let mut current = 0;
+				loop {
+					match self.peek()? {
+						b']' => return None,
+						b',' => self.pos += 1,
+						_ if current == *index => {
+							return if rest.is_empty() { Some(self.pos) } else { self.find(rest) };
+						}
+						_ => {
+							self.skip_value()?;
+							current += 1;
+						}
+					}
+				}
+			}
+			_ => None,
+		}
+	}
+
+	fn string(&mut self) -> Option<String> {
+		let start = self.pos;
+		self.skip_string()?;
+		let raw = std::str::from_utf8(&self.text[start..self.pos]).ok()?;
+		serde_json::from_str(raw).ok()
+	}
+
+	fn skip_string(&mut self) -> Option<()> {
+		if self.peek()? != b'"' {
+			return None;
+		}
+		self.pos += 1;
+		loop {
+			match *self.text.get(self.pos)? {
+				b'\\' => self.pos += 2,
+				b'"' => {
+					self.pos += 1;
+					return Some(());
+				}
+				_ => self.pos += 1,
+			}
+		}
+	}
+
+	fn skip_value(&mut self) -> Option<()> {
+		match self.peek()? {
+			b'"' => self.skip_string(),
+			b'{' | b'[' => {
+				let mut depth = 0;
+				loop {
+					match self.peek()? {
+						b'"' => self.skip_string()?,
+						b'{' | b'[' => {
+							depth += 1;
+							self.pos += 1;
+						}
+						b'}' | b']' => {
+							depth -= 1;
+							self.pos += 1;
+							if depth == 0 {
+								return Some(());
+							}
+						}
+						_ => self.pos += 1,
+					}
+				}
+			}
+			_ => {
+				while !matches!(self.peek()?, b',' | b'}' | b']') {
+					self.pos += 1;
+				}
+				Some(())
+			}
+		}
+	}
+}
+
+#[cfg(feature = "toml")]
+fn toml_line(contents: &str, path: &KeyPath) -> Option<usize> {
+	use toml_edit::Item;
+
+	// Tables only declared through their subtables, as `a` in `[a.b]`, start at their first subtable
+	fn start(item: &Item) -> Option<usize> {
+		item.span()
+			.map(|span| span.start)
+			.or_else(|| item.as_table_like()?.iter().filter_map(|(_, item)| start(item)).min())
+	}
+
+	let document = toml_edit::ImDocument::parse(contents).ok()?;
+	let mut item = document.as_item();
+	for segment in &path.0 {
+		item = match segment {
+			Segment::Key(key) => item.get(key.as_str())?,
+			Segment::Index(index) => item.get(*index)?,
+		};
+	}
+	let offset = start(item)?;
+	Some(contents[..offset].matches('\n').count() + 1)
+}
+
+#[cfg(not(feature = "toml"))]
+fn toml_line(_contents: &str, _path: &KeyPath) -> Option<usize> {
+	None
+}
+
+/// A line of a YAML document which holds content.
+#[derive(Debug, Clone, Copy)]
+struct Line<'a> {
+	number: usize,
+	indent: usize,
+	text: &'a str,
+}
+
+/// Finds `path` in a block of YAML lines, where the first line sets the indentation of the block.
+///
+/// Block mappings and sequences are followed by indentation. A flow value such as `{ left: 1 }`
+/// sits on the line of its key, so the keys inside it resolve to that line.
+fn yaml_line(block: &[Line], path: &[Segment]) -> Option<usize> {
+	let (segment, rest) = path.split_first()?;
+	let indent = block.first()?.indent;
+	let (index, value) = match segment {
+		Segment::Key(key) => block.iter().enumerate().find_map(|(index, line)| {
+			let value = (line.indent == indent).then(|| yaml_key(line.text, key))??;
+			Some((index, value))
+		})?,
+		Segment::Index(item) => block
+			.iter()
+			.enumerate()
+			.filter(|(_, line)| line.indent == indent && line.text.starts_with('-'))
+			.nth(*item)
+			.map(|(index, line)| (index, line.text[1..].trim_start()))?,
+	};
+	let line = block[index];
+	if rest.is_empty() {
+		return Some(line.number);
+	}
+
+	// The value of a key continues on deeper lines, or on sequence items at the same indentation
+	let children = block[index + 1..]
+		.iter()
+		.take_while(|child| {
+			child.indent > indent
+				|| (child.indent == indent
+					&& matches!(segment, Segment::Key(_))
+					&& child.text.starts_with('-'))
+		})
+		.copied();
+	let mut nested: Vec<Line> = Vec::new();
+	if matches!(segment, Segment::Index(_)) && !value.is_empty() {
+		// The first key of an item such as `- url: a` is indented past the dash
+		nested.push(Line {
+			number: line.number,
+			indent: indent + line.text.len() - value.len(),
+			text: value,
+		});
+	}
+	nested.extend(children);
+
+	if nested.is_empty() {
+		let flow = value.trim_start();
+		let keys = rest.iter().all(|segment| match segment {
+			Segment::Key(key) => flow.contains(key.as_str()),
+			Segment::Index(_) => true,
+		});
+		return (flow.starts_with(['{', '[']) && keys).then_some(line.number);
+	}
+	yaml_line(&nested, rest)
+}
+
+/// Matches a line setting `key`, with or without quotes, returning the text after the colon.
+fn yaml_key<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+	let unquoted = text.trim_start_matches(['"', '\'']);
+	let rest = unquoted.strip_prefix(key)?;
+	let rest = if unquoted.len() < text.len() { rest.strip_prefix(['"', '\''])? } else { rest };
+	let value = rest.trim_start().strip_prefix(':')?;
+	(value.is_empty() || value.starts_with(' ')).then(|| value.trim_start())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn lines(format: Format, contents: &str, paths: &[&str]) -> Vec<Option<usize>> {
+		paths
+			.iter()
+			.map(|path| line_of(format, contents, &KeyPath::parse_dotted(path)))
+			.collect()
+	}
+
+	#[test]
+	fn test_json_line_of() {
+		let contents = "{\n  \"db\": { \"left\": 5, \"host\": \"{\\\"\" },\n  \"peers\": [\n    { \"url\": \"a\" },\n    { \"url\": \"b\" }\n  ],\n  \"left\": 1\n}\n";
+		assert_eq!(
+			lines(Format::Json, contents, &["left", "db.left", "db.host", "peers.1.url", "right"]),
+			vec![Some(7), Some(2), Some(2), Some(5), None]
+		);
+	}
+
+	#[test]
+	fn test_yaml_line_of() {
+		let contents = "# add\ndb:\n  left: 5\n  inline: { left: 2 }\npeers:\n- url: a\n- url: b\n  weight: 2\nleft: 1\n";
+		assert_eq!(
+			lines(
+				Format::Yaml,
+				contents,
+				&["left", "db.left", "db.inline.left", "peers.1.weight", "peers.0.url", "right"]
+			),
+			vec![Some(9), Some(3), Some(4), Some(8), Some(6), None]
+		);
+	}
+
+	#[cfg(feature = "toml")]
+	#[test]
+	fn test_toml_line_of() {
+		let contents =
+			"db = { left = 5 }\nleft = 1\n\n[profiles.prod]\nleft = 2\n\n[[peers]]\nurl = \"a\"\n";
+		assert_eq!(
+			lines(
+				Format::Toml,
+				contents,
+				&["left", "db.left", "profiles", "profiles.prod.left", "peers.0.url", "right"]
+			),
+			vec![Some(2), Some(1), Some(4), Some(5), Some(8), None]
+		);
+	}
+}
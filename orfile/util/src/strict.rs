@@ -0,0 +1,137 @@
+use crate::config::{self, Field};
+use crate::path::{KeyPath, Segment};
+use crate::provenance::Provenance;
+use crate::Error;
+use serde_json::{Map, Value};
+
+/// Checks that every key of a merged config is a field of the config type.
+///
+/// Nested keys are only checked below fields marked `#[orfile(nested)]`, since the fields of other
+/// types are not known. The error names the first unknown key, the layers which set it, and the
+/// closest field name when one is near enough to be a likely typo.
+pub fn check(
+	map: &Map<String, Value>,
+	fields: &[Field],
+	provenance: &Provenance,
+) -> Result<(), Error> {
+	check_object(map, fields, &KeyPath::default(), provenance)
+}
+
+fn check_object(
+	map: &Map<String, Value>,
+	fields: &[Field],
+	prefix: &KeyPath,
+	provenance: &Provenance,
+) -> Result<(), Error> {
+	for (key, value) in map {
+		let mut path = prefix.clone();
+		path.0.push(Segment::Key(key.clone()));
+
+		let Some(field) = config::find(fields, key) else {
+			return Err(unknown_key(path, key, fields, provenance));
+		};
+		if field.nested.is_empty() {
+			continue;
+		}
+
+		match value {
+			Value::Object(object) => check_object(object, &field.nested, &path, provenance)?,
+			Value::Array(items) => {
+				for (index, item) in items.iter().enumerate() {
+					if let Value::Object(object) = item {
+						let mut path = path.clone();
+						path.0.push(Segment::Index(index));
+						check_object(object, &field.nested, &path, provenance)?;
+					}
+				}
+			}
+			_ => {}
+		}
+	}
+	Ok(())
+}
+
+fn unknown_key(path: KeyPath, key: &str, fields: &[Field], provenance: &Provenance) -> Error {
+	let sources: Vec<_> = provenance
+		.iter()
+		.filter(|(recorded, _)| recorded.starts_with(&path))
+		.map(|(_, origin)| origin.source.to_string())
+		.collect();
+	let origin =
+		if sources.is_empty() { "an unknown source".to_string() } else { sources.join(", ") };
+
+	Error::UnknownKey { path: path.to_string(), origin, suggestion: suggest(key, fields) }
+}
+
+/// Finds the field name closest to `key`, allowing two edits or a third of the key's length.
+///
+/// A swap of two letters counts as two edits, so `rigth` still suggests `right`.
+fn suggest(key: &str, fields: &[Field]) -> Option<String> {
+	let length = key.chars().count();
+	let threshold = (length / 3).max(2);
+	fields
+		.iter()
+		.map(|field| (levenshtein(key, field.name), field.name))
+		.filter(|(distance, _)| *distance <= threshold && *distance < length)
+		.min_by_key(|(distance, _)| *distance)
+		.map(|(_, name)| name.to_string())
+}
+
+/// The number of single character insertions, deletions and substitutions turning `a` into `b`.
+fn levenshtein(a: &str, b: &str) -> usize {
+	let b: Vec<char> = b.chars().collect();
+	let mut row: Vec<usize> = (0..=b.len()).collect();
+	for (i, ca) in a.chars().enumerate() {
+		let mut previous = row[0];
+		row[0] = i + 1;
+		for (j, cb) in b.iter().enumerate() {
+			let substitution = previous + usize::from(ca != *cb);
+			previous = row[j + 1];
+			row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+		}
+	}
+	row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::provenance::Source;
+	use serde_json::json;
+
+	#[test]
+	fn test_levenshtein() {
+		assert_eq!(levenshtein("rigth", "right"), 2);
+		assert_eq!(levenshtein("left", "left"), 0);
+		assert_eq!(levenshtein("", "abc"), 3);
+	}
+
+	#[test]
+	fn test_check_unknown_key() {
+		let fields = vec![
+			Field::new("left"),
+			Field::new("right"),
+			Field::new("db").with_nested(vec![Field::new("port")]),
+		];
+		let mut provenance = Provenance::default();
+		provenance.record(
+			KeyPath::parse_dotted("rigth"),
+			Source::Arg { flag: "--rigth".to_string(), positions: vec![1] },
+		);
+
+		let map = json!({ "left": 1, "rigth": 2 }).as_object().unwrap().clone();
+		assert_eq!(
+			check(&map, &fields, &provenance).unwrap_err().to_string(),
+			"unknown key `rigth` from arg --rigth at position 1; did you mean `right`?"
+		);
+
+		let map = json!({ "db": { "prot": 1 } }).as_object().unwrap().clone();
+		assert_eq!(
+			check(&map, &fields, &provenance).unwrap_err().to_string(),
+			"unknown key `db.prot` from an unknown source; did you mean `port`?"
+		);
+
+		let map = json!({ "left": 1, "db": { "port": 1 } }).as_object().unwrap().clone();
+		assert!(check(&map, &fields, &provenance).is_ok());
+	}
+}
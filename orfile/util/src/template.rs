@@ -0,0 +1,223 @@
+use crate::config::{Field, Kind, OrfileConfig};
+use crate::format::Format;
+use serde_json::{Map, Value};
+
+/// A key of a starter config file.
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+	key: &'static str,
+	description: Option<&'static str>,
+	value: Template,
+	/// Whether the entry is commented out, for optional fields without a default.
+	commented: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Template {
+	/// A default or a placeholder.
+	Value(Value),
+	/// The keys of a nested config type.
+	Object(Vec<Entry>),
+}
+
+/// Renders a starter config file for a config type.
+///
+/// Fields take their default when they have one and a placeholder such as `"<integer>"` when they
+/// are required. Other fields are left out of JSON and commented out of TOML and YAML, where doc
+/// comments are written as comments above their keys.
+pub fn for_config<T: OrfileConfig>(format: Format) -> String {
+	let entries = entries(&T::fields());
+	match format {
+		Format::Json => {
+			let value = to_value(&entries);
+			format!("{}\n", serde_json::to_string_pretty(&value).unwrap_or_default())
+		}
+		Format::Toml => {
+			let mut lines = header(T::description());
+			toml_table(&entries, &[], false, &mut lines);
+			finish(lines)
+		}
+		Format::Yaml => {
+			let mut lines = header(T::description());
+			yaml_entries(&entries, 0, false, &mut lines);
+			finish(lines)
+		}
+	}
+}
+
+fn entries(fields: &[Field]) -> Vec<Entry> {
+	fields
+		.iter()
+		.map(|field| {
+			let value = match (field.default, &field.kind) {
+				(Some(default), _) => Template::Value(default()),
+				(None, Kind::Object | Kind::Any | Kind::Option(_)) if !field.nested.is_empty() => {
+					Template::Object(entries(&field.nested))
+				}
+				(None, kind) => Template::Value(placeholder(kind)),
+			};
+			Entry {
+				key: field.name,
+				description: field.description,
+				value,
+				commented: field.default.is_none() && !field.required,
+			}
+		})
+		.collect()
+}
+
+fn placeholder(kind: &Kind) -> Value {
+	match kind {
+		Kind::Any => Value::String("<value>".to_string()),
+		Kind::Boolean => Value::String("<boolean>".to_string()),
+		Kind::Integer { .. } => Value::String("<integer>".to_string()),
+		Kind::Number => Value::String("<number>".to_string()),
+		Kind::String => Value::String("<string>".to_string()),
+		Kind::Array(_) => Value::Array(Vec::new()),
+		Kind::Object => Value::Object(Map::new()),
+		Kind::Option(inner) => placeholder(inner),
+	}
+}
+
+fn to_value(entries: &[Entry]) -> Value {
+	let mut map = Map::new();
+	for entry in entries.iter().filter(|entry| !entry.commented) {
+		let value = match &entry.value {
+			Template::Value(value) => value.clone(),
+			Template::Object(entries) => to_value(entries),
+		};
+		map.insert(entry.key.to_string(), value);
+	}
+	Value::Object(map)
+}
+
+fn header(description: Option<&str>) -> Vec<String> {
+	let mut lines = Vec::new();
+	if let Some(description) = description {
+		comment(description, "", &mut lines);
+		lines.push(String::new());
+	}
+	lines
+}
+
+fn finish(mut lines: Vec<String>) -> String {
+	while lines.last().is_some_and(String::is_empty) {
+		lines.pop();
+	}
+	lines.push(String::new());
+	lines.join("\n")
+}
+
+fn comment(text: &str, indent: &str, lines: &mut Vec<String>) {
+	for line in text.lines() {
+		lines.push(format!("{}# {}", indent, line).trim_end().to_string());
+	}
+}
+
+/// Writes a key bare when it only has letters, digits, `_` and `-`, and quoted otherwise.
+fn key(key: &str) -> String {
+	if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+		key.to_string()
+	} else {
+		Value::String(key.to_string()).to_string()
+	}
+}
+
+/// Writes one table, with its values first and then its nested tables, as TOML requires.
+fn toml_table(entries: &[Entry], path: &[&str], commented: bool, lines: &mut Vec<String>) {
+	for entry in entries {
+		let Template::Value(value) = &entry.value else {
+			continue;
+		};
+		let prefix = if commented || entry.commented || value.is_null() { "# " } else { "" };
+		if let Some(description) = entry.description {
+			comment(description, "", lines);
+		}
+		lines.push(format!("{}{} = {}", prefix, key(entry.key), toml_value(value)));
+	}
+
+	for entry in entries {
+		let Template::Object(nested) = &entry.value else {
+			continue;
+		};
+		let commented = commented || entry.commented;
+		let path = [path, &[entry.key]].concat();
+		lines.push(String::new());
+		if let Some(description) = entry.description {
+			comment(description, "", lines);
+		}
+		let header = path.iter().map(|segment| key(segment)).collect::<Vec<_>>().join(".");
+		lines.push(format!("{}[{}]", if commented { "# " } else { "" }, header));
+		toml_table(nested, &path, commented, lines);
+	}
+}
+
+fn toml_value(value: &Value) -> String {
+	match value {
+		Value::Array(items) => {
+			format!("[{}]", items.iter().map(toml_value).collect::<Vec<_>>().join(", "))
+		}
+		Value::Object(map) if map.is_empty() => "{}".to_string(),
+		Value::Object(map) => format!(
+			"{{ {} }}",
+			map.iter()
+				.map(|(k, v)| format!("{} = {}", key(k), toml_value(v)))
+				.collect::<Vec<_>>()
+				.join(", ")
+		),
+		// JSON strings, numbers and bools are valid TOML, and TOML has no null
+		value => value.to_string(),
+	}
+}
+
+/// Writes entries as YAML, with values in JSON's flow syntax which YAML also accepts.
+fn yaml_entries(entries: &[Entry], depth: usize, commented: bool, lines: &mut Vec<String>) {
+	let indent = "  ".repeat(depth);
+	for entry in entries {
+		let prefix = if commented || entry.commented { "# " } else { "" };
+		if let Some(description) = entry.description {
+			comment(description, &indent, lines);
+		}
+		match &entry.value {
+			Template::Value(value) => {
+				lines.push(format!("{}{}{}: {}", indent, prefix, key(entry.key), value));
+			}
+			Template::Object(nested) => {
+				lines.push(format!("{}{}{}:", indent, prefix, key(entry.key)));
+				yaml_entries(nested, depth + 1, commented || entry.commented, lines);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::config::fixture::Args;
+	use serde_json::json;
+
+	#[test]
+	fn test_json_template() {
+		let template = for_config::<Args>(Format::Json);
+		assert_eq!(
+			serde_json::from_str::<Value>(&template).unwrap(),
+			json!({ "left": "<integer>", "name": "add", "db": { "port": "<integer>" } })
+		);
+	}
+
+	#[test]
+	fn test_toml_template() {
+		assert_eq!(
+			for_config::<Args>(Format::Toml),
+			"# The arguments\n\n# The left number\nleft = \"<integer>\"\nname = \"add\"\n# ratio = \"<number>\"\n# peers = []\n\n[db]\nport = \"<integer>\"\n"
+		);
+	}
+
+	#[test]
+	fn test_yaml_template() {
+		assert_eq!(
+			for_config::<Args>(Format::Yaml),
+			"# The arguments\n\n# The left number\nleft: \"<integer>\"\nname: \"add\"\n# ratio: \"<number>\"\n# peers: []\ndb:\n  port: \"<integer>\"\n"
+		);
+	}
+}